@@ -0,0 +1,36 @@
+-- Reports are kept as json so the season structs can change without a schema change.
+-- The season column keeps each year's reports apart, old seasons are never touched.
+
+CREATE TABLE match_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    season INTEGER NOT NULL,
+    team_number INTEGER NOT NULL,
+    recording_team_number INTEGER NOT NULL,
+    team_member TEXT NOT NULL,
+    event TEXT NOT NULL,
+    match_level TEXT NOT NULL,
+    match_number INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    report TEXT NOT NULL
+);
+
+CREATE INDEX match_reports_lookup
+    ON match_reports (season, event, team_number, match_level, match_number);
+
+CREATE TABLE pit_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    season INTEGER NOT NULL,
+    team_number INTEGER NOT NULL,
+    recording_team INTEGER NOT NULL,
+    team_member TEXT NOT NULL,
+    event TEXT NOT NULL,
+    report TEXT NOT NULL
+);
+
+CREATE INDEX pit_reports_lookup ON pit_reports (season, event, team_number);
+
+CREATE TABLE auth (
+    team INTEGER PRIMARY KEY,
+    key TEXT NOT NULL,
+    auth TEXT NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Named keys, each one is the json of an ApiKey with only a hash of the secret in it.
+-- The plaintext keys in auth are hashed into here by the server the first time it starts.
+
+CREATE TABLE api_keys (
+    id TEXT PRIMARY KEY,
+    team INTEGER NOT NULL,
+    key TEXT NOT NULL
+);
+
+CREATE INDEX api_keys_team ON api_keys (team);
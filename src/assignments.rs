@@ -1,12 +1,21 @@
+//! Hands out scouting assignments.
+//! Each scouting group gets a MatchScoutAssignments per event that tracks who is watching which
+//! robot, the robots themselves come from the qualification schedule on TBA.
+
+use std::{collections::HashMap, fmt};
+
 use anyhow::*;
-use log::*;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use utoipa::ToSchema;
 
-pub struct Assignment {
-    position: Position,
-    team_number: u32,
-}
+use crate::data::{season::Season, theblueallience::TbaMatchData, Complevel, MatchNumber};
 
-pub enum Position {
+///One of the six robots in a match
+#[derive(Debug, Clone, Copy, EnumIter, Hash, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum Slot {
     RED1,
     RED2,
     RED3,
@@ -15,65 +24,567 @@ pub enum Position {
     BLUE3,
 }
 
-pub struct GameManager {
-    event: String,
+impl Slot {
+    ///The team in this slot, `teams` is red 1-3 followed by blue 1-3
+    pub fn team(&self, teams: &[u32; 6]) -> u32 {
+        teams[*self as usize]
+    }
+}
 
-    //idk if this will be used or if it will just be included in the event string
-    field: String,
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        //pad so the printable schedules line up
+        f.pad(match self {
+            Slot::RED1 => "RED1",
+            Slot::RED2 => "RED2",
+            Slot::RED3 => "RED3",
+            Slot::BLUE1 => "BLUE1",
+            Slot::BLUE2 => "BLUE2",
+            Slot::BLUE3 => "BLUE3",
+        })
+    }
+}
 
-    current_match: Match,
+///What a scout should be watching
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScoutingAssignment {
+    pub event: String,
+    pub match_number: MatchNumber,
+    pub slot: Slot,
+    pub team_number: u32,
+}
 
-    client: reqwest::Client,
-    apikey: String,
+///The qualification schedule of an event by match number
+#[derive(Debug, Clone, Default)]
+pub struct EventSchedule {
+    matches: HashMap<u32, [u32; 6]>,
 }
 
-impl GameManager {
-    ///constructs the game manager
-    pub async fn new(event: String, apikey: String) -> Result<Self> {
-        //check that the first api works and that the event exists.
+impl EventSchedule {
+    ///Only keeps the qualifiers, playoff alliances are not known far enough ahead to plan around
+    pub fn new<S: Season>(matches: Vec<TbaMatchData<S>>) -> Self {
+        Self {
+            matches: matches
+                .into_iter()
+                .filter(|m| m.match_number.level == Complevel::Qualifier)
+                .map(|m| {
+                    let [r1, r2, r3] = m.red_allience;
+                    let [b1, b2, b3] = m.blue_allience;
+                    (m.match_number.number, [r1, r2, r3, b1, b2, b3])
+                })
+                .collect(),
+        }
+    }
 
-        let gm = Self {
-            event,
-            field: "".to_string(),
-            current_match: Match::new(0),
-            client: reqwest::Client::new(),
-            apikey: format!("Basic {}", apikey),
-        };
-        gm.client
-            .get("https://frc-api.firstinspires.org/status")
-            .header("Authorization", &gm.apikey)
-            .send()
-            .await?
-            .error_for_status()?;
-
-        info!("Gamemanager started");
-
-        Ok(gm)
-    }
-    ///internal function to check if current match is still valid
-    async fn check_match(&mut self) {
-        //gets a list of the matches and searches for the last sceduled match with a start time.
-        //Then check how long that match has been running for. If it has been running longer then a
-        //spesified time, then consider it dead and move to the next match.
-        //
-        //
-        //https://frc-api.firstinspires.org/v3.0/:season/matches/:eventCode?tournamentLevel=&teamNumber=&matchNumber=&start=&end=
-        //
-        //https://frc-api-docs.firstinspires.org/#733f4607-ab40-4e00-b3e1-36cfb1a2e77e
-        //
-        //keep a cached match start time for 15 seconds so there is no risk of being rate limited
-        //when all the scouts start new rounds.
-    }
-    pub async fn get_assignment(&self) {}
-}
-
-///This struct will be used to track what positions still need to be filled
+    pub fn teams(&self, match_num: u32) -> Option<&[u32; 6]> {
+        self.matches.get(&match_num)
+    }
+
+    ///Every qualifier in the order they are played
+    pub fn match_numbers(&self) -> Vec<u32> {
+        let mut numbers: Vec<u32> = self.matches.keys().copied().collect();
+        numbers.sort();
+        numbers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+}
+
+///Who is scouting what in every match for one group of scouts at one event
+#[derive(Debug, Clone, Default)]
+pub struct MatchScoutAssignments {
+    matches: HashMap<u32, Match>,
+}
+
+impl MatchScoutAssignments {
+    ///Gives the scout a robot in the match. Asking again for the same match gives the same robot.
+    pub fn get_assignment(&mut self, match_num: u32, scout: &str) -> Slot {
+        self.matches.entry(match_num).or_default().assign(scout)
+    }
+
+    ///How many scouts each robot in the match has
+    pub fn coverage(&self, match_num: u32) -> HashMap<Slot, u32> {
+        let current = self.matches.get(&match_num);
+        Slot::iter()
+            .map(|slot| (slot, current.map_or(0, |m| m.scout_count(&slot) as u32)))
+            .collect()
+    }
+}
+
+///How well a match is covered in the global pool
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MatchCoverage {
+    pub event: String,
+    pub match_number: MatchNumber,
+    pub scouts: HashMap<Slot, u32>,
+    ///how many scouts every robot should have
+    pub scouts_per_robot: u32,
+    ///true once every robot has at least scouts_per_robot scouts
+    pub covered: bool,
+}
+
+///This struct tracks what positions still need to be filled
+#[derive(Debug, Clone, Default)]
 struct Match {
-    match_number: i32,
+    scouts: HashMap<Slot, Vec<String>>,
 }
 
 impl Match {
-    fn new(match_number: i32) -> Self {
-        Self { match_number }
+    fn slot_of(&self, scout: &str) -> Option<Slot> {
+        self.scouts
+            .iter()
+            .find(|(_, scouts)| scouts.iter().any(|s| s == scout))
+            .map(|(slot, _)| *slot)
+    }
+
+    fn scout_count(&self, slot: &Slot) -> usize {
+        self.scouts.get(slot).map_or(0, Vec::len)
+    }
+
+    fn assign(&mut self, scout: &str) -> Slot {
+        if let Some(slot) = self.slot_of(scout) {
+            return slot;
+        }
+
+        //the emptiest slot, ties go in slot order so all six are covered before any is doubled
+        let slot = Slot::iter()
+            .min_by_key(|slot| self.scout_count(slot))
+            .expect("there are always six slots");
+
+        self.scouts.entry(slot).or_default().push(scout.to_string());
+        slot
+    }
+}
+
+///Scout `on` matches in a row then take `off` matches off
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub struct ShiftRules {
+    pub on: u32,
+    pub off: u32,
+}
+
+///The scouts a team has and how long their shifts are
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ShiftRequest {
+    pub scouts: Vec<String>,
+    pub rules: ShiftRules,
+}
+
+///Who scouts what for one team over the whole qualification block
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ShiftSchedule {
+    pub event: String,
+    pub team_number: u32,
+    pub rules: ShiftRules,
+    ///the scouts that are currently around, in the order they joined
+    pub roster: Vec<RosterScout>,
+    pub matches: Vec<ScheduledMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RosterScout {
+    pub name: String,
+    ///where the scout is in the rotation, they are on when
+    ///`(qualifier index + offset) % (on + off) < on`
+    pub offset: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledMatch {
+    pub match_number: u32,
+    pub shifts: Vec<Shift>,
+    ///robots nobody on the team is watching, this happens when fewer than six scouts are on
+    pub uncovered: Vec<Shift>,
+}
+
+///One scout watching one robot, the scout is empty for uncovered robots
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Shift {
+    pub scout: String,
+    pub slot: Slot,
+    pub team_number: u32,
+}
+
+impl ShiftSchedule {
+    pub fn new(
+        event: String,
+        team_number: u32,
+        request: ShiftRequest,
+        schedule: &EventSchedule,
+    ) -> Result<Self> {
+        if request.rules.on == 0 {
+            return Err(anyhow!("a shift has to be at least one match long"));
+        }
+        if request.scouts.is_empty() {
+            return Err(anyhow!("there are no scouts to make a schedule for"));
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        for scout in request.scouts {
+            if !names.contains(&scout) {
+                names.push(scout);
+            }
+        }
+
+        //staggered so about the same number of scouts are on in every match
+        let cycle = request.rules.on + request.rules.off;
+        let count = names.len() as u32;
+        let roster = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| RosterScout {
+                name,
+                offset: i as u32 * cycle / count,
+            })
+            .collect();
+
+        let mut shifts = Self {
+            event,
+            team_number,
+            rules: request.rules,
+            roster,
+            matches: Vec::new(),
+        };
+        shifts.plan_from(0, schedule);
+        Ok(shifts)
+    }
+
+    ///Adds a scout that showed up late, they start a shift at `match_num`.
+    ///Everyone else keeps their rotation and checking in again changes nothing.
+    pub fn check_in(
+        &mut self,
+        scout: String,
+        match_num: u32,
+        schedule: &EventSchedule,
+    ) -> Result<()> {
+        let start = schedule
+            .match_numbers()
+            .iter()
+            .position(|number| *number == match_num)
+            .ok_or(
+                anyhow!(StatusCode::BAD_REQUEST)
+                    .context(format!("match {} is not in the schedule", match_num)),
+            )? as u32;
+        if self.roster.iter().any(|s| s.name == scout) {
+            return Ok(());
+        }
+
+        let cycle = self.rules.on + self.rules.off;
+        self.roster.push(RosterScout {
+            name: scout,
+            offset: (cycle - start % cycle) % cycle,
+        });
+        self.plan_from(match_num, schedule);
+        Ok(())
+    }
+
+    ///Takes a scout off every match from `match_num` on. Nobody else's shifts move so the robots
+    ///they would have watched show up as uncovered.
+    pub fn leave(&mut self, scout: &str, match_num: u32, schedule: &EventSchedule) -> Result<()> {
+        if !self.roster.iter().any(|s| s.name == scout) {
+            return Err(anyhow!("{} is not on the roster", scout));
+        }
+        self.roster.retain(|s| s.name != scout);
+        self.plan_from(match_num, schedule);
+        Ok(())
+    }
+
+    ///The robot the scout is watching in the match, none when they are off
+    pub fn shift_for(&self, scout: &str, match_num: u32) -> Option<&Shift> {
+        self.matches
+            .iter()
+            .find(|m| m.match_number == match_num)?
+            .shifts
+            .iter()
+            .find(|s| s.scout == scout)
+    }
+
+    ///Redoes every match from `match_num` on and leaves the ones before it alone.
+    ///The scouts that are on get robots the same way check ins do.
+    fn plan_from(&mut self, match_num: u32, schedule: &EventSchedule) {
+        self.matches.retain(|m| m.match_number < match_num);
+
+        let cycle = self.rules.on + self.rules.off;
+
+        for (index, number) in schedule.match_numbers().into_iter().enumerate() {
+            if number < match_num {
+                continue;
+            }
+            let Some(teams) = schedule.teams(number) else {
+                continue;
+            };
+
+            let mut current = Match::default();
+            let shifts: Vec<Shift> = self
+                .roster
+                .iter()
+                .filter(|scout| (index as u32 + scout.offset) % cycle < self.rules.on)
+                .map(|scout| {
+                    let slot = current.assign(&scout.name);
+                    Shift {
+                        scout: scout.name.clone(),
+                        slot,
+                        team_number: slot.team(teams),
+                    }
+                })
+                .collect();
+
+            let uncovered = Slot::iter()
+                .filter(|slot| current.scout_count(slot) == 0)
+                .map(|slot| Shift {
+                    scout: "".to_string(),
+                    slot,
+                    team_number: slot.team(teams),
+                })
+                .collect();
+
+            self.matches.push(ScheduledMatch {
+                match_number: number,
+                shifts,
+                uncovered,
+            });
+        }
+    }
+
+    ///One row per scout per match
+    pub fn to_csv(&self) -> String {
+        let mut csv = "match,scout,slot,team\n".to_string();
+        for scheduled in self.matches.iter() {
+            for shift in scheduled.shifts.iter().chain(scheduled.uncovered.iter()) {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    scheduled.match_number,
+                    csv_field(&shift.scout),
+                    shift.slot,
+                    shift.team_number
+                ));
+            }
+        }
+        csv
+    }
+
+    ///A page per scout listing the matches they are on, meant to be printed and handed out
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Scouting shifts for team {} at {} ({} on, {} off)\n",
+            self.team_number, self.event, self.rules.on, self.rules.off
+        );
+
+        for scout in self.roster.iter() {
+            text.push_str(&format!("\n{}\n", scout.name));
+            for scheduled in self.matches.iter() {
+                if let Some(shift) = scheduled.shifts.iter().find(|s| s.scout == scout.name) {
+                    text.push_str(&format!(
+                        "  Q{:<4} {:<6} {}\n",
+                        scheduled.match_number, shift.slot, shift.team_number
+                    ));
+                }
+            }
+        }
+
+        let uncovered: Vec<(u32, &Shift)> = self
+            .matches
+            .iter()
+            .flat_map(|m| m.uncovered.iter().map(move |s| (m.match_number, s)))
+            .collect();
+        if !uncovered.is_empty() {
+            text.push_str("\nNot covered\n");
+            for (number, shift) in uncovered {
+                text.push_str(&format!(
+                    "  Q{:<4} {:<6} {}\n",
+                    number, shift.slot, shift.team_number
+                ));
+            }
+        }
+
+        text
+    }
+}
+
+//scout names are typed in by people so they might have commas or quotes in them
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///`count` qualifiers numbered from 1, match n has teams n*10+1 to n*10+6
+    fn schedule(count: u32) -> EventSchedule {
+        EventSchedule {
+            matches: (1..=count)
+                .map(|n| (n, [1, 2, 3, 4, 5, 6].map(|t| n * 10 + t)))
+                .collect(),
+        }
+    }
+
+    fn request(scouts: &[&str], on: u32, off: u32) -> ShiftRequest {
+        ShiftRequest {
+            scouts: scouts.iter().map(|s| s.to_string()).collect(),
+            rules: ShiftRules { on, off },
+        }
+    }
+
+    fn on_in(shifts: &ShiftSchedule, match_num: u32) -> Vec<&str> {
+        shifts
+            .matches
+            .iter()
+            .find(|m| m.match_number == match_num)
+            .unwrap()
+            .shifts
+            .iter()
+            .map(|s| s.scout.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn fills_every_robot_before_doubling_up() {
+        let mut assignments = MatchScoutAssignments::default();
+        let slots: Vec<Slot> = ["a", "b", "c", "d", "e", "f"]
+            .iter()
+            .map(|scout| assignments.get_assignment(1, scout))
+            .collect();
+        for slot in Slot::iter() {
+            assert!(slots.contains(&slot));
+        }
+
+        assert_eq!(assignments.get_assignment(1, "g"), Slot::RED1);
+        assert_eq!(assignments.get_assignment(1, "h"), Slot::RED2);
+        let coverage = assignments.coverage(1);
+        assert_eq!(coverage[&Slot::RED1], 2);
+        assert_eq!(coverage[&Slot::BLUE3], 1);
+    }
+
+    #[test]
+    fn asking_again_gives_the_same_robot() {
+        let mut assignments = MatchScoutAssignments::default();
+        let first = assignments.get_assignment(1, "a");
+        assignments.get_assignment(1, "b");
+        assert_eq!(assignments.get_assignment(1, "a"), first);
+        assert_eq!(assignments.coverage(1)[&first], 1);
+    }
+
+    #[test]
+    fn coverage_of_an_unassigned_match_is_zero() {
+        let assignments = MatchScoutAssignments::default();
+        assert!(assignments.coverage(3).values().all(|n| *n == 0));
+    }
+
+    #[test]
+    fn shifts_rotate() {
+        let shifts = ShiftSchedule::new(
+            "e".to_string(),
+            254,
+            request(&["a", "b"], 2, 2),
+            &schedule(6),
+        )
+        .unwrap();
+        //staggered half a cycle apart so someone is always on
+        assert_eq!(on_in(&shifts, 1), vec!["a"]);
+        assert_eq!(on_in(&shifts, 2), vec!["a"]);
+        assert_eq!(on_in(&shifts, 3), vec!["b"]);
+        assert_eq!(on_in(&shifts, 4), vec!["b"]);
+        assert_eq!(on_in(&shifts, 5), vec!["a"]);
+
+        let shift = shifts.shift_for("a", 2).unwrap();
+        assert_eq!(
+            shift.team_number,
+            shift.slot.team(&[21, 22, 23, 24, 25, 26])
+        );
+        assert!(shifts.shift_for("b", 1).is_none());
+        assert_eq!(shifts.matches[0].uncovered.len(), 5);
+    }
+
+    #[test]
+    fn shift_rules_are_checked() {
+        assert!(
+            ShiftSchedule::new("e".to_string(), 254, request(&["a"], 0, 2), &schedule(3)).is_err()
+        );
+        assert!(
+            ShiftSchedule::new("e".to_string(), 254, request(&[], 1, 0), &schedule(3)).is_err()
+        );
+
+        let shifts = ShiftSchedule::new(
+            "e".to_string(),
+            254,
+            request(&["a", "a"], 1, 0),
+            &schedule(3),
+        )
+        .unwrap();
+        assert_eq!(shifts.roster.len(), 1);
+    }
+
+    #[test]
+    fn check_in_starts_a_shift_and_leaves_the_past_alone() {
+        let schedule = schedule(6);
+        let mut shifts =
+            ShiftSchedule::new("e".to_string(), 254, request(&["a"], 1, 0), &schedule).unwrap();
+        shifts.check_in("late".to_string(), 3, &schedule).unwrap();
+
+        assert_eq!(on_in(&shifts, 2), vec!["a"]);
+        assert_eq!(on_in(&shifts, 3), vec!["a", "late"]);
+        assert_eq!(on_in(&shifts, 6), vec!["a", "late"]);
+    }
+
+    #[test]
+    fn check_in_past_the_schedule_is_refused() {
+        let schedule = schedule(4);
+        let mut shifts =
+            ShiftSchedule::new("e".to_string(), 254, request(&["a"], 1, 0), &schedule).unwrap();
+        let refused = shifts.check_in("late".to_string(), 5, &schedule);
+        assert_eq!(
+            refused.unwrap_err().downcast_ref::<StatusCode>(),
+            Some(&StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(shifts.roster.len(), 1);
+    }
+
+    #[test]
+    fn checking_in_again_changes_nothing() {
+        let schedule = schedule(6);
+        let mut shifts =
+            ShiftSchedule::new("e".to_string(), 254, request(&["a", "b"], 2, 2), &schedule)
+                .unwrap();
+        let before = on_in(&shifts, 4)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        shifts.check_in("a".to_string(), 4, &schedule).unwrap();
+        assert_eq!(on_in(&shifts, 4), before);
+        assert_eq!(shifts.roster.len(), 2);
+    }
+
+    #[test]
+    fn late_scout_is_on_from_the_match_they_came() {
+        let schedule = schedule(6);
+        let mut shifts =
+            ShiftSchedule::new("e".to_string(), 254, request(&["a"], 2, 2), &schedule).unwrap();
+        shifts.check_in("late".to_string(), 2, &schedule).unwrap();
+
+        assert!(shifts.shift_for("late", 1).is_none());
+        assert!(shifts.shift_for("late", 2).is_some());
+        assert!(shifts.shift_for("late", 3).is_some());
+        assert!(shifts.shift_for("late", 4).is_none());
+    }
+
+    #[test]
+    fn leaving_uncovers_their_robots_from_then_on() {
+        let schedule = schedule(4);
+        let mut shifts =
+            ShiftSchedule::new("e".to_string(), 254, request(&["a", "b"], 1, 0), &schedule)
+                .unwrap();
+        shifts.leave("b", 3, &schedule).unwrap();
+
+        assert_eq!(on_in(&shifts, 2), vec!["a", "b"]);
+        assert_eq!(on_in(&shifts, 3), vec!["a"]);
+        assert_eq!(shifts.matches[2].uncovered.len(), 5);
+        assert!(shifts.leave("nobody", 3, &schedule).is_err());
     }
 }
@@ -0,0 +1,379 @@
+//! How close the scouts get to the official results.
+//! TBA only counts most things per alliance, so a scout's report is judged by adding it to what
+//! the other scouts said about the other two robots and comparing that with the official total.
+//! The per robot results (leave, endgame) are checked against the report on their own.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{season::Season, TeamMatchReport};
+
+///How well one scout, or every scout from one team, matched the official results
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AccuracyScore {
+    pub recording_team_number: u32,
+    ///none when this is the score for the whole team
+    pub team_member: Option<String>,
+    ///0 to 1, the mean of every check below
+    pub accuracy: f64,
+    pub reports: u32,
+    ///the mean score per field, to see what a scout needs to work on
+    pub fields: HashMap<String, f64>,
+}
+
+///The accuracy of every scout and every recording team over an event
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScoutingAccuracy {
+    pub event: String,
+    ///played matches with a score breakdown and at least one scouted report
+    pub matches_checked: u32,
+    ///worst first so the ones that need help are at the top
+    pub scouts: Vec<AccuracyScore>,
+    pub teams: Vec<AccuracyScore>,
+}
+
+///The reports for each robot in an alliance in driver station order, next to that alliance's
+///score breakdown
+pub type AllianceReports<S> = ([Vec<TeamMatchReport<S>>; 3], <S as Season>::ScoreBreakdown);
+
+///(field, score from 0 to 1) for everything checked in one report
+type Checks = Vec<(String, f64)>;
+
+impl ScoutingAccuracy {
+    ///How much to trust a scout, from their own accuracy or their team's when they have not been
+    ///checked yet. None when neither has been.
+    pub fn weight(&self, recording_team_number: u32, team_member: &str) -> Option<f64> {
+        self.scouts
+            .iter()
+            .find(|s| {
+                s.recording_team_number == recording_team_number
+                    && s.team_member.as_deref() == Some(team_member)
+            })
+            .or_else(|| {
+                self.teams
+                    .iter()
+                    .find(|t| t.recording_team_number == recording_team_number)
+            })
+            .map(|score| score.accuracy)
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    reports: u32,
+    fields: HashMap<String, (f64, u32)>,
+}
+
+impl Tally {
+    fn add(&mut self, checks: &[(String, f64)]) {
+        self.reports += 1;
+        for (field, score) in checks {
+            let entry = self.fields.entry(field.clone()).or_insert((0.0, 0));
+            entry.0 += score;
+            entry.1 += 1;
+        }
+    }
+
+    fn score(&self, recording_team_number: u32, team_member: Option<String>) -> AccuracyScore {
+        let (total, count) = self
+            .fields
+            .values()
+            .fold((0.0, 0), |(total, count), (sum, n)| {
+                (total + sum, count + n)
+            });
+
+        AccuracyScore {
+            recording_team_number,
+            team_member,
+            //nothing to check counts as right, there is no reason to distrust them yet
+            accuracy: if count > 0 { total / count as f64 } else { 1.0 },
+            reports: self.reports,
+            fields: self
+                .fields
+                .iter()
+                .map(|(field, (sum, n))| (field.clone(), sum / *n as f64))
+                .collect(),
+        }
+    }
+}
+
+///Collects the checks of every report over an event
+#[derive(Default)]
+pub struct AccuracyTally {
+    by_scout: HashMap<(u32, String), Tally>,
+    by_team: HashMap<u32, Tally>,
+    matches: u32,
+}
+
+impl AccuracyTally {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Checks one played match, alliances without a breakdown are left out
+    pub fn add_match<S: Season>(&mut self, alliances: &[AllianceReports<S>]) {
+        let mut any_reports = false;
+        for (robots, breakdown) in alliances {
+            for (station, checks) in check_alliance::<S>(robots, breakdown)
+                .into_iter()
+                .enumerate()
+            {
+                for (report, checks) in robots[station].iter().zip(checks) {
+                    self.by_scout
+                        .entry((report.recording_team_number, report.team_member.clone()))
+                        .or_default()
+                        .add(&checks);
+                    self.by_team
+                        .entry(report.recording_team_number)
+                        .or_default()
+                        .add(&checks);
+                    any_reports = true;
+                }
+            }
+        }
+
+        if any_reports {
+            self.matches += 1;
+        }
+    }
+
+    pub fn finish(self, event: String) -> ScoutingAccuracy {
+        let mut scouts: Vec<AccuracyScore> = self
+            .by_scout
+            .iter()
+            .map(|((team, member), tally)| tally.score(*team, Some(member.clone())))
+            .collect();
+        let mut teams: Vec<AccuracyScore> = self
+            .by_team
+            .iter()
+            .map(|(team, tally)| tally.score(*team, None))
+            .collect();
+        scouts.sort_by(|a, b| a.accuracy.total_cmp(&b.accuracy));
+        teams.sort_by(|a, b| a.accuracy.total_cmp(&b.accuracy));
+
+        ScoutingAccuracy {
+            event,
+            matches_checked: self.matches,
+            scouts,
+            teams,
+        }
+    }
+}
+
+///The checks for every report in one alliance by station and then in the same order as the
+///reports
+fn check_alliance<S: Season>(
+    robots: &[Vec<TeamMatchReport<S>>; 3],
+    breakdown: &S::ScoreBreakdown,
+) -> Vec<Vec<Checks>> {
+    let totals = S::official_totals(breakdown);
+
+    //what the scouts said on average about each robot, the sums need one value per robot
+    let means: Vec<Option<HashMap<&'static str, f64>>> = robots
+        .iter()
+        .map(|reports| {
+            if reports.is_empty() {
+                return None;
+            }
+            let mut sums: HashMap<&'static str, f64> = HashMap::new();
+            for report in reports {
+                for (field, value) in S::alliance_fields(&report.data) {
+                    *sums.entry(field).or_insert(0.0) += value;
+                }
+            }
+            Some(
+                sums.into_iter()
+                    .map(|(field, sum)| (field, sum / reports.len() as f64))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    robots
+        .iter()
+        .enumerate()
+        .map(|(station, reports)| {
+            reports
+                .iter()
+                .map(|report| {
+                    let mut checks: Checks = S::check_robot(&report.data, breakdown, station)
+                        .into_iter()
+                        .map(|check| (check.field, if check.agrees { 1.0 } else { 0.0 }))
+                        .collect();
+
+                    //a robot nobody scouted leaves a hole in the sum, the totals can not be used
+                    //for the alliance then
+                    let others: Option<Vec<&HashMap<&'static str, f64>>> = means
+                        .iter()
+                        .enumerate()
+                        .filter(|(other, _)| *other != station)
+                        .map(|(_, mean)| mean.as_ref())
+                        .collect();
+                    if let Some(others) = others {
+                        let fields: HashMap<&'static str, f64> =
+                            S::alliance_fields(&report.data).into_iter().collect();
+                        for (field, official) in &totals {
+                            let Some(value) = fields.get(field) else {
+                                continue;
+                            };
+                            let scouted = value
+                                + others
+                                    .iter()
+                                    .map(|mean| mean.get(field).copied().unwrap_or(0.0))
+                                    .sum::<f64>();
+                            checks.push((field.to_string(), total_score(scouted, *official)));
+                        }
+                    }
+
+                    checks
+                })
+                .collect()
+        })
+        .collect()
+}
+
+///1 when the scouted total is right, going down to 0 when it is off by as much as the official
+///total (or by 1 when the official total is 0)
+fn total_score(scouted: f64, official: f64) -> f64 {
+    1.0 - ((scouted - official).abs() / official.max(1.0)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        super::season::reefscape::{test_report, Reefscape},
+        *,
+    };
+
+    fn report(
+        recording_team_number: u32,
+        team_member: &str,
+        leave: bool,
+        processor: u32,
+        net: u32,
+    ) -> TeamMatchReport<Reefscape> {
+        let mut report = test_report(recording_team_number, team_member);
+        report.data.leave = leave;
+        report.data.algae_processor_teleop = processor;
+        report.data.algae_net_teleop = net;
+        report
+    }
+
+    ///Every robot left and parked, 3 algae in the processor and 6 in the net
+    fn breakdown() -> <Reefscape as Season>::ScoreBreakdown {
+        serde_json::from_value(json!({
+            "autoLineRobot1": "Yes", "autoLineRobot2": "Yes", "autoLineRobot3": "Yes",
+            "endGameRobot1": "Parked", "endGameRobot2": "Parked", "endGameRobot3": "Parked",
+            "wallAlgaeCount": 3,
+            "netAlgaeCount": 6,
+        }))
+        .unwrap()
+    }
+
+    fn score<'a>(scores: &'a [AccuracyScore], member: Option<&str>) -> &'a AccuracyScore {
+        scores
+            .iter()
+            .find(|s| s.team_member.as_deref() == member)
+            .unwrap()
+    }
+
+    #[test]
+    fn right_scouts_are_fully_accurate() {
+        let mut tally = AccuracyTally::new();
+        tally.add_match::<Reefscape>(&[(
+            [
+                vec![report(254, "a", true, 1, 2)],
+                vec![report(254, "b", true, 1, 2)],
+                vec![report(254, "c", true, 1, 2)],
+            ],
+            breakdown(),
+        )]);
+        let accuracy = tally.finish("2025casj".to_string());
+
+        assert_eq!(accuracy.matches_checked, 1);
+        assert_eq!(accuracy.scouts.len(), 3);
+        for scout in &accuracy.scouts {
+            assert_eq!(scout.accuracy, 1.0);
+            assert_eq!(scout.fields["algae_net"], 1.0);
+        }
+        assert_eq!(accuracy.teams[0].reports, 3);
+    }
+
+    #[test]
+    fn wrong_scout_is_worst_and_first() {
+        let mut tally = AccuracyTally::new();
+        tally.add_match::<Reefscape>(&[(
+            [
+                vec![report(254, "a", true, 1, 2), report(1678, "x", false, 4, 2)],
+                vec![report(254, "b", true, 1, 2)],
+                vec![report(254, "c", true, 1, 2)],
+            ],
+            breakdown(),
+        )]);
+        let accuracy = tally.finish("2025casj".to_string());
+
+        let worst = &accuracy.scouts[0];
+        assert_eq!(worst.team_member.as_deref(), Some("x"));
+        assert_eq!(worst.fields["leave"], 0.0);
+        //4 + 1 + 1 against 3 is off by the whole total
+        assert_eq!(worst.fields["algae_processor"], 0.0);
+        assert!(worst.accuracy < score(&accuracy.scouts, Some("b")).accuracy);
+        assert_eq!(accuracy.teams[0].recording_team_number, 1678);
+    }
+
+    #[test]
+    fn unscouted_robot_leaves_out_the_totals() {
+        let mut tally = AccuracyTally::new();
+        tally.add_match::<Reefscape>(&[(
+            [vec![report(254, "a", true, 1, 2)], vec![], vec![]],
+            breakdown(),
+        )]);
+        let accuracy = tally.finish("2025casj".to_string());
+
+        let fields = &accuracy.scouts[0].fields;
+        assert!(fields.contains_key("leave"));
+        assert!(fields.contains_key("endgame"));
+        assert!(!fields.contains_key("algae_net"));
+    }
+
+    #[test]
+    fn no_reports_is_not_a_checked_match() {
+        let mut tally = AccuracyTally::new();
+        tally.add_match::<Reefscape>(&[([vec![], vec![], vec![]], breakdown())]);
+        assert_eq!(tally.finish("2025casj".to_string()).matches_checked, 0);
+    }
+
+    #[test]
+    fn weight_falls_back_to_the_team() {
+        let mut tally = AccuracyTally::new();
+        tally.add_match::<Reefscape>(&[(
+            [
+                vec![report(254, "a", false, 1, 2)],
+                vec![report(254, "b", true, 1, 2)],
+                vec![report(254, "c", true, 1, 2)],
+            ],
+            breakdown(),
+        )]);
+        let accuracy = tally.finish("2025casj".to_string());
+
+        let a = score(&accuracy.scouts, Some("a")).accuracy;
+        assert_eq!(accuracy.weight(254, "a"), Some(a));
+        assert_eq!(
+            accuracy.weight(254, "new"),
+            Some(accuracy.teams[0].accuracy)
+        );
+        assert_eq!(accuracy.weight(1678, "a"), None);
+    }
+
+    #[test]
+    fn total_scores() {
+        assert_eq!(total_score(3.0, 3.0), 1.0);
+        assert_eq!(total_score(2.0, 4.0), 0.5);
+        assert_eq!(total_score(10.0, 4.0), 0.0);
+        assert_eq!(total_score(1.0, 0.0), 0.0);
+    }
+}
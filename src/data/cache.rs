@@ -0,0 +1,190 @@
+//! Shared response cache for the TBA and Statbotics clients.
+//! Every response is kept for a ttl picked per endpoint. Once that runs out the request is sent
+//! again with the `ETag`/`Last-Modified` it came with, so an unchanged response costs a 304.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::*;
+use reqwest::{
+    header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Client, Request, RequestBuilder, StatusCode,
+};
+use serde::de::DeserializeOwned;
+
+use super::pack::EventPack;
+
+#[derive(Clone, Default)]
+pub struct HttpCache {
+    //one lock per url so a crowd asking for the same thing waits on a single request
+    entries: Arc<Mutex<HashMap<String, CacheSlot>>>,
+    //set when running from an event pack, nothing goes over the network then
+    pack: Option<Arc<HashMap<String, String>>>,
+}
+
+type CacheSlot = Arc<tokio::sync::Mutex<Option<CacheEntry>>>;
+
+///The urls have event keys and team numbers from the requests in them, so without a cap anyone
+///could fill the memory by asking for made up ones
+const MAX_ENTRIES: usize = 2048;
+///How many of the oldest responses go when the cache is full, so it is not a sweep every request
+const EVICT_AT_ONCE: usize = MAX_ENTRIES / 8;
+
+struct CacheEntry {
+    body: String,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    fetched: Instant,
+}
+
+impl HttpCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Answers every request from the pack and never touches the network
+    pub fn from_pack(pack: EventPack) -> Self {
+        Self {
+            pack: Some(Arc::new(pack.responses)),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.pack.is_some()
+    }
+
+    ///Every response body in the cache by url, used to build event packs
+    pub fn responses(&self) -> Result<HashMap<String, String>> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("the cache lock is poisoned"))?;
+
+        let mut responses = HashMap::new();
+        for (url, slot) in entries.iter() {
+            let entry = slot
+                .try_lock()
+                .map_err(|_| anyhow!("{} is still being fetched", url))?;
+            if let Some(entry) = entry.as_ref() {
+                responses.insert(url.clone(), entry.body.clone());
+            }
+        }
+        Ok(responses)
+    }
+
+    ///Sends the request unless there is a response for the same url younger than `ttl`
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        ttl: Duration,
+    ) -> Result<T> {
+        let (client, request) = request.build_split();
+        let request = request?;
+
+        if let Some(pack) = &self.pack {
+            let body = pack
+                .get(request.url().as_str())
+                .ok_or(anyhow!("{} is not in the event pack", request.url()))?;
+            return Ok(serde_json::from_str(body)?);
+        }
+
+        let url = request.url().to_string();
+        let slot = self.slot(&url)?;
+        let mut entry = slot.lock().await;
+
+        let data = self.fetch(client, request, &mut entry, ttl).await;
+        if data.is_err() && entry.is_none() {
+            //nothing worth keeping, a url that never works should not hold a slot forever
+            self.forget(&url, &slot);
+        }
+        data
+    }
+
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        client: Client,
+        mut request: Request,
+        entry: &mut Option<CacheEntry>,
+        ttl: Duration,
+    ) -> Result<T> {
+        if let Some(cached) = entry.as_ref() {
+            if cached.fetched.elapsed() < ttl {
+                return Ok(serde_json::from_str(&cached.body)?);
+            }
+
+            let headers = request.headers_mut();
+            if let Some(etag) = &cached.etag {
+                headers.insert(IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        let response = client.execute(request).await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = entry.as_mut() {
+                cached.fetched = Instant::now();
+                return Ok(serde_json::from_str(&cached.body)?);
+            }
+        }
+
+        let response = response.error_for_status()?;
+        let etag = response.headers().get(ETAG).cloned();
+        let last_modified = response.headers().get(LAST_MODIFIED).cloned();
+        let body = response.text().await?;
+
+        //parsed before it is stored so a broken response is not served again
+        let data = serde_json::from_str(&body)?;
+        *entry = Some(CacheEntry {
+            body,
+            etag,
+            last_modified,
+            fetched: Instant::now(),
+        });
+
+        Ok(data)
+    }
+
+    fn slot(&self, url: &str) -> Result<CacheSlot> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("the cache lock is poisoned"))?;
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(url) {
+            evict_oldest(&mut entries);
+        }
+        Ok(entries.entry(url.to_string()).or_default().clone())
+    }
+
+    fn forget(&self, url: &str, slot: &CacheSlot) {
+        if let Result::Ok(mut entries) = self.entries.lock() {
+            //someone else may have put a new slot in already
+            if entries.get(url).is_some_and(|s| Arc::ptr_eq(s, slot)) {
+                entries.remove(url);
+            }
+        }
+    }
+}
+
+///Drops the responses that were fetched longest ago. Slots that are being fetched right now are
+///left alone.
+fn evict_oldest(entries: &mut HashMap<String, CacheSlot>) {
+    let mut fetched: Vec<(String, Option<Instant>)> = entries
+        .iter()
+        .filter_map(|(url, slot)| {
+            let entry = slot.try_lock().ok()?;
+            Some((url.clone(), entry.as_ref().map(|e| e.fetched)))
+        })
+        .collect();
+    //empty slots sort first, they have nothing in them anyway
+    fetched.sort_by_key(|(_, fetched)| *fetched);
+    for (url, _) in fetched.into_iter().take(EVICT_AT_ONCE) {
+        entries.remove(&url);
+    }
+}
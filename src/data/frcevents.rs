@@ -0,0 +1,339 @@
+//! Client for the official FRC Events API (frc-api.firstinspires.org).
+//! TBA gets its data from here so this is the freshest source there is, it just needs a token
+//! from https://frc-events.firstinspires.org/services/API.
+
+use anyhow::*;
+use async_trait::async_trait;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    season::{ScoreBreakdown, Season},
+    source::{EventSource, Ranking},
+    theblueallience::TbaMatchData,
+    Allience, Complevel, MatchNumber,
+};
+
+const FRC_API: &str = "https://frc-api.firstinspires.org/v3.0";
+
+//playoff matches are numbered straight through, the first 13 are the double elimination bracket
+//and the rest are the finals
+const LAST_BRACKET_MATCH: u32 = 13;
+
+///The username and token for the FRC Events API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrcApiAuth {
+    pub username: String,
+    pub key: String,
+}
+
+#[derive(Clone)]
+pub struct FrcEvents {
+    client: reqwest::Client,
+    auth: FrcApiAuth,
+}
+
+impl FrcEvents {
+    pub fn new(auth: FrcApiAuth) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            auth,
+        }
+    }
+
+    ///Checks that the api is up and the token works
+    pub async fn check(&self) -> Result<()> {
+        self.client
+            .get(format!("{}/", FRC_API))
+            .basic_auth(&self.auth.username, Some(&self.auth.key))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: String) -> Result<T> {
+        Ok(self
+            .client
+            .get(format!("{}/{}", FRC_API, path))
+            .basic_auth(&self.auth.username, Some(&self.auth.key))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<T>()
+            .await?)
+    }
+
+    ///The schedule and the results for one tournament level, `filter` is added to both queries
+    async fn get_matches<S: Season>(
+        &self,
+        event: &str,
+        level: &str,
+        filter: &str,
+    ) -> Result<Vec<TbaMatchData<S>>> {
+        let (season, code) = event_code(event)?;
+
+        let schedule: FrcSchedule = self
+            .get(format!(
+                "{}/schedule/{}?tournamentLevel={}{}",
+                season, code, level, filter
+            ))
+            .await?;
+        let results: FrcMatches = self
+            .get(format!(
+                "{}/matches/{}?tournamentLevel={}{}",
+                season, code, level, filter
+            ))
+            .await?;
+
+        schedule
+            .schedule
+            .into_iter()
+            .map(|scheduled| {
+                let result = results.matches.iter().find(|m| {
+                    m.match_number == scheduled.match_number
+                        && m.tournament_level == scheduled.tournament_level
+                });
+                scheduled.into_match_data(result)
+            })
+            .collect()
+    }
+}
+
+///Splits a TBA event key like `2024casj` into the season and the FRC event code
+fn event_code(event: &str) -> Result<(&str, &str)> {
+    if event.len() <= 4 || !event.is_char_boundary(4) {
+        return Err(anyhow!("{} is not a valid event key", event));
+    }
+    let (season, code) = event.split_at(4);
+    if !season.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("{} does not start with a season", event));
+    }
+    Ok((season, code))
+}
+
+fn level_query(level: &Complevel) -> &'static str {
+    match level {
+        Complevel::Practice => "Practice",
+        Complevel::Qualifier => "Qualification",
+        Complevel::Semifinal | Complevel::Final => "Playoff",
+    }
+}
+
+///The FRC API gives times in the event's own time zone without saying which one it is. The server
+///is almost always sitting at the event so its time zone is used.
+fn parse_local_time(time: &Option<String>) -> Option<u64> {
+    let naive = NaiveDateTime::parse_from_str(time.as_ref()?, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    Some(Local.from_local_datetime(&naive).earliest()?.timestamp() as u64)
+}
+
+#[async_trait]
+impl<S: Season> EventSource<S> for FrcEvents {
+    fn name(&self) -> &'static str {
+        "frc"
+    }
+
+    async fn get_match_data(
+        &self,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TbaMatchData<S>> {
+        //playoff numbers on our side restart for the finals
+        let number = match match_number.level {
+            Complevel::Final => match_number.number + LAST_BRACKET_MATCH,
+            _ => match_number.number,
+        };
+
+        self.get_matches(
+            &event,
+            level_query(&match_number.level),
+            &format!("&start={}&end={}", number, number),
+        )
+        .await?
+        .pop()
+        .ok_or(anyhow!("{} has no match {:?}", event, match_number))
+    }
+
+    async fn get_match_data_list(&self, event: String) -> Result<Vec<TbaMatchData<S>>> {
+        let mut matches = self.get_matches(&event, "Qualification", "").await?;
+        matches.extend(self.get_matches(&event, "Playoff", "").await?);
+        Ok(matches)
+    }
+
+    async fn get_rankings(&self, event: String) -> Result<Vec<Ranking>> {
+        let (season, code) = event_code(&event)?;
+        let rankings: FrcRankings = self.get(format!("{}/rankings/{}", season, code)).await?;
+
+        Ok(rankings
+            .rankings
+            .into_iter()
+            .map(|r| Ranking {
+                rank: r.rank,
+                team_number: r.team_number,
+                wins: r.wins,
+                losses: r.losses,
+                ties: r.ties,
+                matches_played: r.matches_played,
+                ranking_score: r.sort_order1,
+            })
+            .collect())
+    }
+
+    async fn get_team_list(&self, event: String) -> Result<Vec<u32>> {
+        let (season, code) = event_code(&event)?;
+
+        //the team list comes in pages of 65
+        let mut teams = Vec::new();
+        let mut page = 1;
+        loop {
+            let response: FrcTeams = self
+                .get(format!("{}/teams?eventCode={}&page={}", season, code, page))
+                .await?;
+            teams.extend(response.teams.into_iter().map(|t| t.team_number));
+
+            if page >= response.page_total {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(teams)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FrcSchedule {
+    schedule: Vec<FrcScheduledMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcScheduledMatch {
+    start_time: Option<String>,
+    match_number: u32,
+    tournament_level: String,
+    teams: Vec<FrcStation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcStation {
+    team_number: Option<u32>,
+    station: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FrcMatches {
+    matches: Vec<FrcMatchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcMatchResult {
+    actual_start_time: Option<String>,
+    post_result_time: Option<String>,
+    match_number: u32,
+    tournament_level: String,
+    score_red_final: Option<u32>,
+    score_red_foul: Option<u32>,
+    score_red_auto: Option<u32>,
+    score_blue_final: Option<u32>,
+    score_blue_foul: Option<u32>,
+    score_blue_auto: Option<u32>,
+}
+
+impl FrcScheduledMatch {
+    fn into_match_data<S: Season>(
+        self,
+        result: Option<&FrcMatchResult>,
+    ) -> Result<TbaMatchData<S>> {
+        let level = match (self.tournament_level.as_str(), self.match_number) {
+            ("Practice", _) => Complevel::Practice,
+            ("Qualification", _) => Complevel::Qualifier,
+            ("Playoff", n) if n <= LAST_BRACKET_MATCH => Complevel::Semifinal,
+            ("Playoff", _) => Complevel::Final,
+            (level, _) => return Err(anyhow!("unknown tournament level {}", level)),
+        };
+        let number = match level {
+            Complevel::Final => self.match_number - LAST_BRACKET_MATCH,
+            _ => self.match_number,
+        };
+
+        let station = |name: &str| {
+            self.teams
+                .iter()
+                .find(|t| t.station == name)
+                .and_then(|t| t.team_number)
+                .unwrap_or(0)
+        };
+
+        //only counts as played once the result is posted
+        let result = result.filter(|r| r.post_result_time.is_some());
+        let red_score = result.and_then(|r| r.score_red_final).unwrap_or(0);
+        let blue_score = result.and_then(|r| r.score_blue_final).unwrap_or(0);
+
+        Ok(TbaMatchData {
+            match_number: MatchNumber { number, level },
+            winning_allience: match result {
+                Some(_) if red_score > blue_score => Some(Allience::RED),
+                Some(_) if blue_score > red_score => Some(Allience::BLUE),
+                _ => None,
+            },
+            red_allience: [station("Red1"), station("Red2"), station("Red3")],
+            blue_allience: [station("Blue1"), station("Blue2"), station("Blue3")],
+            red_score,
+            blue_score,
+            red_score_breakdown: result.map(|r| {
+                S::ScoreBreakdown::from_totals(
+                    r.score_red_final,
+                    r.score_red_auto,
+                    r.score_red_foul,
+                )
+            }),
+            blue_score_breakdown: result.map(|r| {
+                S::ScoreBreakdown::from_totals(
+                    r.score_blue_final,
+                    r.score_blue_auto,
+                    r.score_blue_foul,
+                )
+            }),
+            time: parse_local_time(&self.start_time),
+            actual_time: result.and_then(|r| parse_local_time(&r.actual_start_time)),
+            //the FRC API does not predict anything
+            predicted_time: None,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FrcRankings {
+    rankings: Vec<FrcRanking>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcRanking {
+    rank: u32,
+    team_number: u32,
+    sort_order1: f64,
+    wins: u32,
+    losses: u32,
+    ties: u32,
+    matches_played: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcTeams {
+    teams: Vec<FrcTeam>,
+    page_total: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrcTeam {
+    team_number: u32,
+}
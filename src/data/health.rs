@@ -0,0 +1,119 @@
+//! Which upstream apis are up right now.
+//! A source that fails with a network error or a 5xx is marked down and skipped until the
+//! background probe sees it answering again, so a dead api costs one timeout and not one per
+//! request. Anything else (a team that is not at the event, a match that is not played yet) is
+//! the request's fault and does not count against the source.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::*;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+///How often the sources that are down get checked again
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Default)]
+pub struct SourceHealth {
+    //the error that took each source down, sources that are up are not in here
+    down: Arc<RwLock<HashMap<&'static str, String>>>,
+}
+
+impl SourceHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_down(&self, source: &'static str) -> bool {
+        self.down
+            .read()
+            .map(|down| down.contains_key(source))
+            .unwrap_or(false)
+    }
+
+    ///Runs the request unless the source is down, an outage takes the source down
+    pub async fn call<T>(
+        &self,
+        source: &'static str,
+        request: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        if let Some(error) = self.down_error(source) {
+            return Err(anyhow!("{} is down: {}", source, error));
+        }
+
+        let result = request.await;
+        if let Err(e) = &result {
+            if is_outage(e) {
+                self.mark_down(source, e);
+            }
+        }
+        result
+    }
+
+    ///Takes the result of a health check, the source is up if it passed
+    pub fn record(&self, source: &'static str, check: Result<()>) {
+        match check {
+            Result::Ok(()) => {
+                let back = self
+                    .down
+                    .write()
+                    .is_ok_and(|mut down| down.remove(source).is_some());
+                if back {
+                    info!("{} is back up", source);
+                }
+            }
+            Err(e) => self.mark_down(source, &e),
+        }
+    }
+
+    fn mark_down(&self, source: &'static str, error: &Error) {
+        if let Result::Ok(mut down) = self.down.write() {
+            if down.insert(source, error.to_string()).is_none() {
+                warn!(
+                    "{} is down, it will be skipped until it is back: {}",
+                    source, error
+                );
+            }
+        }
+    }
+
+    fn down_error(&self, source: &'static str) -> Option<String> {
+        self.down.read().ok()?.get(source).cloned()
+    }
+}
+
+///Network trouble and server errors mean the source is down, a 404 just means there is no data
+fn is_outage(error: &Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+            e.is_connect()
+                || e.is_timeout()
+                || e.status().is_some_and(|status| status.is_server_error())
+        })
+    })
+}
+
+///Whether one upstream source answered a request, sent along with anything built from several
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SourceStatus {
+    pub source: String,
+    pub ok: bool,
+    ///why the source did not answer, the fields it fills in are left empty
+    pub error: Option<String>,
+}
+
+impl SourceStatus {
+    pub fn of<T>(source: &str, result: &Result<T>) -> Self {
+        Self {
+            source: source.to_string(),
+            ok: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+}
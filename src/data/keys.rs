@@ -0,0 +1,335 @@
+//! Named api keys, one per scout device so a lost tablet can be revoked on its own.
+//! Only an argon2 hash of the secret is kept. The key handed out is `{id}.{secret}`, the id finds
+//! the hash so checking a key is one argon2 verify and not one for every key the team has.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::*;
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::ToSchema;
+
+use super::{
+    openscout::{Auth, AuthLevel},
+    session::Caller,
+};
+
+const ID_LEN: usize = 12;
+const SECRET_LEN: usize = 32;
+///How long a key that was just checked is trusted without another argon2 verify
+const VERIFIED_KEY_TIME: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub _id: String,
+    pub team: u32,
+    ///what the key is for, like the tablet it is on
+    pub name: String,
+    ///argon2 PHC string, never the key itself
+    pub hash: String,
+    pub auth: AuthLevel,
+    //unix epoch
+    pub created: u64,
+    pub expires: Option<u64>,
+    pub revoked: bool,
+    ///the whole team is disabled, unlike a revoke it can be undone
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+///Everything about a key but the hash
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KeyInfo {
+    pub id: String,
+    pub team: u32,
+    pub name: String,
+    pub auth: AuthLevel,
+    pub created: u64,
+    pub expires: Option<u64>,
+    pub revoked: bool,
+    pub disabled: bool,
+}
+
+///A new or rotated key. This is the only time it can be seen, only the hash is kept.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IssuedKey {
+    ///goes in the `key` header
+    pub key: String,
+    pub info: KeyInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct KeyRequest {
+    ///the scout's name for a SCOUT key, they can only get assignments and post reports as it
+    pub name: String,
+    ///TEAM when not given, only an admin can make ADMIN keys
+    pub auth: Option<AuthLevel>,
+    ///the key never expires without it
+    pub expires_in_days: Option<u64>,
+}
+
+///What to change about a key, anything left out stays as it is
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct KeyUpdate {
+    pub name: Option<String>,
+    pub auth: Option<AuthLevel>,
+    ///from now, not from when the key was made
+    pub expires_in_days: Option<u64>,
+}
+
+///A team and every key it has
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TeamAccount {
+    pub team: u32,
+    ///none of the team's keys work, its reports are all still there
+    pub disabled: bool,
+    pub keys: Vec<KeyInfo>,
+}
+
+impl ApiKey {
+    ///Makes a key with a random id and secret, the secret is only ever in the returned key
+    pub fn issue(team: u32, request: KeyRequest) -> Result<(Self, String)> {
+        let created = now();
+        let id = random_string(ID_LEN);
+        let secret = random_string(SECRET_LEN);
+
+        let key = Self {
+            _id: id.clone(),
+            team,
+            name: request.name,
+            hash: hash_secret(&secret)?,
+            auth: request.auth.unwrap_or(AuthLevel::TEAM),
+            created,
+            expires: request
+                .expires_in_days
+                .map(|days| created + days * 24 * 60 * 60),
+            revoked: false,
+            disabled: false,
+        };
+        Ok((key, format!("{}.{}", id, secret)))
+    }
+
+    ///The key a team had before keys had names. Its id is the team number so the tablets that
+    ///already have it keep working.
+    pub fn from_legacy(auth: Auth) -> Result<Self> {
+        Ok(Self {
+            _id: auth._id.to_string(),
+            team: auth._id,
+            name: "default".to_string(),
+            hash: hash_secret(&auth.key)?,
+            auth: auth.auth,
+            created: now(),
+            expires: None,
+            revoked: false,
+            disabled: false,
+        })
+    }
+
+    ///Gives the key a new secret and keeps the id and name. An expiring key gets as long as it
+    ///had when it was made.
+    pub fn rotate(&mut self) -> Result<String> {
+        let secret = random_string(SECRET_LEN);
+        let created = now();
+        self.hash = hash_secret(&secret)?;
+        self.expires = self
+            .expires
+            .map(|expires| created + expires.saturating_sub(self.created));
+        self.created = created;
+        Ok(format!("{}.{}", self._id, secret))
+    }
+
+    pub fn update(&mut self, update: KeyUpdate) {
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(auth) = update.auth {
+            self.auth = auth;
+        }
+        if let Some(days) = update.expires_in_days {
+            self.expires = Some(now() + days * 24 * 60 * 60);
+        }
+    }
+
+    pub fn usable(&self) -> bool {
+        !self.revoked && !self.disabled && self.expires.is_none_or(|expires| now() < expires)
+    }
+
+    ///Slow on purpose, run it off the async threads
+    pub fn verify(&self, secret: &str) -> bool {
+        PasswordHash::new(&self.hash)
+            .map(|hash| {
+                Argon2::default()
+                    .verify_password(secret.as_bytes(), &hash)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn caller(&self) -> Caller {
+        Caller {
+            team: self.team,
+            key_id: self._id.clone(),
+            name: self.name.clone(),
+            auth: self.auth.clone(),
+        }
+    }
+
+    pub fn info(&self) -> KeyInfo {
+        KeyInfo {
+            id: self._id.clone(),
+            team: self.team,
+            name: self.name.clone(),
+            auth: self.auth.clone(),
+            created: self.created,
+            expires: self.expires,
+            revoked: self.revoked,
+            disabled: self.disabled,
+        }
+    }
+}
+
+///Splits the `key` header into the key id and the secret. Keys from before there were names do
+///not have an id in them, those are looked up by the team number. Once one of those is rotated it
+///is `{team}.{secret}`, its id is still the team number.
+pub fn split_key(team: u32, key: &str) -> (String, &str) {
+    match key.split_once('.') {
+        Some((id, secret))
+            if (id.len() == ID_LEN && id.chars().all(|c| c.is_ascii_alphanumeric()))
+                || id == team.to_string() =>
+        {
+            (id.to_string(), secret)
+        }
+        _ => (team.to_string(), key),
+    }
+}
+
+///Keys that were checked a moment ago so not every request is an argon2 verify. One is shared by
+///every season so a revoke is seen by all of them straight away.
+///The secrets are not kept, only an hmac of them with a key that never leaves the process.
+#[derive(Clone)]
+pub struct VerifiedKeys {
+    verified: Arc<Mutex<HashMap<String, VerifiedKey>>>,
+    mac_key: Arc<Vec<u8>>,
+}
+
+///When it was checked, the hmac of the secret it was checked with and the key
+type VerifiedKey = (Instant, Vec<u8>, ApiKey);
+
+impl VerifiedKeys {
+    pub fn new() -> Self {
+        let mut mac_key = vec![0; 32];
+        rand::thread_rng().fill_bytes(&mut mac_key);
+        Self {
+            verified: Arc::new(Mutex::new(HashMap::new())),
+            mac_key: Arc::new(mac_key),
+        }
+    }
+
+    pub fn get(&self, id: &str, secret: &str) -> Option<ApiKey> {
+        let verified = self.verified.lock().ok()?;
+        let (checked, known, key) = verified.get(id)?;
+        if checked.elapsed() >= VERIFIED_KEY_TIME {
+            return None;
+        }
+        //verify_slice takes the same time however much of it matches
+        self.mac(secret)?.verify_slice(known).ok()?;
+        Some(key.clone())
+    }
+
+    pub fn insert(&self, secret: &str, key: ApiKey) {
+        let Some(mac) = self.mac(secret) else {
+            return;
+        };
+        if let Result::Ok(mut verified) = self.verified.lock() {
+            let known = mac.finalize().into_bytes().to_vec();
+            verified.insert(key._id.clone(), (Instant::now(), known, key));
+        }
+    }
+
+    pub fn forget(&self, id: &str) {
+        if let Result::Ok(mut verified) = self.verified.lock() {
+            verified.remove(id);
+        }
+    }
+
+    fn mac(&self, secret: &str) -> Option<Hmac<Sha256>> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.mac_key).ok()?;
+        mac.update(secret.as_bytes());
+        Some(mac)
+    }
+}
+
+fn hash_secret(secret: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)
+        .map_err(|e| anyhow!("unable to hash the key: {}", e))?
+        .to_string())
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy() -> ApiKey {
+        ApiKey::from_legacy(Auth {
+            _id: 254,
+            key: "oldkey".to_string(),
+            auth: AuthLevel::TEAM,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn issued_key_verifies() {
+        let request = KeyRequest {
+            name: "tablet".to_string(),
+            auth: None,
+            expires_in_days: None,
+        };
+        let (key, presented) = ApiKey::issue(254, request).unwrap();
+        let (id, secret) = split_key(254, &presented);
+        assert_eq!(id, key._id);
+        assert!(key.verify(secret));
+    }
+
+    #[test]
+    fn legacy_key_is_found_by_team() {
+        let key = legacy();
+        assert_eq!(split_key(254, "oldkey"), ("254".to_string(), "oldkey"));
+        assert!(key.verify("oldkey"));
+    }
+
+    #[test]
+    fn rotated_legacy_key_verifies() {
+        let mut key = legacy();
+        let presented = key.rotate().unwrap();
+        let (id, secret) = split_key(254, &presented);
+        assert_eq!(id, key._id);
+        assert!(key.verify(secret));
+        assert!(!key.verify("oldkey"));
+    }
+}
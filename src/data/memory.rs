@@ -0,0 +1,451 @@
+//! In-memory backend for events with no database at all.
+//! Everything lives in ram and is written to a json snapshot every so often, the snapshot is
+//! loaded again on startup so a restart does not lose any reports.
+//! Like the sqlite backend the reports are kept as json tagged with their season, so one snapshot
+//! holds every season the server hosts.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use anyhow::*;
+use async_trait::async_trait;
+use log::{error, info, warn};
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{
+    keys::ApiKey,
+    openscout::Auth,
+    season::Season,
+    store::{ScoutStore, Shared, Viewer},
+    MatchNumber, TeamMatchReport, TeamPitReport,
+};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Snapshot {
+    match_reports: Vec<StoredReport>,
+    pit_reports: Vec<StoredReport>,
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+    //plaintext keys from older snapshots, they are hashed into keys when loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    auth: Vec<Auth>,
+}
+
+///A report and the season it is from. The report fields stay at the top level so snapshots from
+///before reports were tagged still load.
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredReport {
+    #[serde(default = "untagged_season")]
+    season: u32,
+    #[serde(flatten)]
+    report: Map<String, Value>,
+}
+
+//everything written before the seasons were split up was from 2024
+fn untagged_season() -> u32 {
+    2024
+}
+
+impl StoredReport {
+    fn new<T: Serialize>(season: u32, report: &T) -> Result<Self> {
+        match serde_json::to_value(report)? {
+            Value::Object(report) => Ok(Self { season, report }),
+            _ => Err(anyhow!("reports are always json objects")),
+        }
+    }
+
+    //every lookup is for one team at one event so those are checked before parsing anything
+    fn is_for(&self, season: u32, team_number: u32, event: &str) -> bool {
+        self.season == season
+            && self.report.get("team_number").and_then(Value::as_u64) == Some(team_number as u64)
+            && self.report.get("event").and_then(Value::as_str) == Some(event)
+    }
+
+    fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_value(Value::Object(self.report.clone()))?)
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryStore {
+    data: Arc<RwLock<Snapshot>>,
+    //set whenever something changes since the last flush
+    dirty: Arc<AtomicBool>,
+    snapshot_path: Option<PathBuf>,
+}
+
+impl MemoryStore {
+    ///Loads the snapshot at `snapshot_path` if there is one and starts flushing to it every
+    ///`flush_interval`. Without a path nothing is ever written to disk.
+    pub fn new(snapshot_path: Option<PathBuf>, flush_interval: Duration) -> Result<Self> {
+        let mut snapshot = match &snapshot_path {
+            Some(path) if path.exists() => {
+                let snapshot: Snapshot = serde_json::from_str(&fs::read_to_string(path)?)?;
+                info!(
+                    "loaded {} match reports and {} pit reports from {}",
+                    snapshot.match_reports.len(),
+                    snapshot.pit_reports.len(),
+                    path.display()
+                );
+                snapshot
+            }
+            _ => Snapshot::default(),
+        };
+
+        let legacy = std::mem::take(&mut snapshot.auth);
+        let dirty = !legacy.is_empty();
+        for auth in legacy {
+            info!("hashed the key for team {}", auth._id);
+            snapshot.keys.push(ApiKey::from_legacy(auth)?);
+        }
+
+        let store = Self {
+            data: Arc::new(RwLock::new(snapshot)),
+            dirty: Arc::new(AtomicBool::new(dirty)),
+            snapshot_path,
+        };
+
+        if store.snapshot_path.is_some() {
+            let flusher = store.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(flush_interval);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = flusher.write_snapshot() {
+                        error!("unable to write the snapshot: {}", e);
+                    }
+                }
+            });
+        }
+
+        Ok(store)
+    }
+
+    ///Writes the snapshot if anything changed. The file is replaced in one go so a crash halfway
+    ///through a write can not leave a broken snapshot behind.
+    pub fn write_snapshot(&self) -> Result<()> {
+        let Some(path) = &self.snapshot_path else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let json = self.read(serde_json::to_string)??;
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .inspect_err(|_| self.dirty.store(true, Ordering::SeqCst))?;
+
+        Ok(())
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Snapshot) -> T) -> Result<T> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("the report store lock is poisoned"))?;
+        Ok(f(&data))
+    }
+
+    fn write<T>(&self, f: impl FnOnce(&mut Snapshot) -> T) -> Result<T> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("the report store lock is poisoned"))?;
+        self.dirty.store(true, Ordering::SeqCst);
+        Ok(f(&mut data))
+    }
+
+    ///Every report from the season about the team at the event that passes `filter`, in the order
+    ///they were added
+    fn find<T: DeserializeOwned + Shared>(
+        &self,
+        reports: impl Fn(&Snapshot) -> &Vec<StoredReport>,
+        viewer: Viewer,
+        season: u32,
+        team_number: u32,
+        event: &str,
+        filter: impl Fn(&T) -> bool,
+    ) -> Result<Vec<T>> {
+        let candidates: Vec<StoredReport> = self.read(|s| {
+            reports(s)
+                .iter()
+                .filter(|r| r.is_for(season, team_number, event))
+                .cloned()
+                .collect()
+        })?;
+
+        let mut data = Vec::new();
+        for stored in candidates.iter() {
+            //one bad record should not take every other report for the team down with it
+            let report = match stored.parse::<T>() {
+                Result::Ok(report) => report,
+                Err(e) => {
+                    warn!(
+                        "skipping a {} report for team {} at {} that does not parse: {}",
+                        season, team_number, event, e
+                    );
+                    continue;
+                }
+            };
+            let Some(report) = report.for_viewer(viewer) else {
+                continue;
+            };
+            if filter(&report) {
+                data.push(report);
+            }
+        }
+
+        if data.is_empty() {
+            return Err(anyhow!(StatusCode::NO_CONTENT));
+        }
+        Ok(data)
+    }
+
+    fn find_match<S: Season>(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: &str,
+        filter: impl Fn(&TeamMatchReport<S>) -> bool,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find(
+            |s| &s.match_reports,
+            viewer,
+            S::YEAR,
+            team_number,
+            event,
+            filter,
+        )
+    }
+
+    ///The newest report by timestamp, the later insert wins a tie
+    fn find_last_match<S: Season>(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: &str,
+        filter: impl Fn(&TeamMatchReport<S>) -> bool,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_match(viewer, team_number, event, filter)?
+            .into_iter()
+            .max_by_key(|r| r.timestamp)
+            .ok_or(anyhow!(StatusCode::NO_CONTENT))
+    }
+
+    fn find_pit<S: Season>(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: &str,
+        filter: impl Fn(&TeamPitReport<S>) -> bool,
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.find(
+            |s| &s.pit_reports,
+            viewer,
+            S::YEAR,
+            team_number,
+            event,
+            filter,
+        )
+    }
+
+    //pit reports have no timestamp so the last one added is the newest
+    fn find_last_pit<S: Season>(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: &str,
+        filter: impl Fn(&TeamPitReport<S>) -> bool,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_pit(viewer, team_number, event, filter)?
+            .pop()
+            .ok_or(anyhow!(StatusCode::NO_CONTENT))
+    }
+}
+
+#[async_trait]
+impl<S: Season> ScoutStore<S> for MemoryStore {
+    async fn post_team_match_data(&self, data: TeamMatchReport<S>) -> Result<()> {
+        let report = StoredReport::new(S::YEAR, &data)?;
+        self.write(|s| s.match_reports.push(report))
+    }
+
+    async fn post_team_pit_data(&self, data: TeamPitReport<S>) -> Result<()> {
+        let report = StoredReport::new(S::YEAR, &data)?;
+        self.write(|s| s.pit_reports.push(report))
+    }
+
+    async fn get_last_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_last_match(viewer, team_number, &event, |r| {
+            r.match_number == match_number
+        })
+    }
+
+    async fn get_all_team_match_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find_match(viewer, team_number, &event, |r| {
+            r.match_number == match_number && r.recording_team_number == recording_team
+        })
+    }
+
+    async fn get_team_match_data_by_induvidual(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        recording_induvidual: String,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_last_match(viewer, team_number, &event, |r| {
+            r.match_number == match_number
+                && r.recording_team_number == recording_team
+                && r.team_member == recording_induvidual
+        })
+    }
+
+    async fn get_last_team_match_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_last_match(viewer, team_number, &event, |r| {
+            r.match_number == match_number && r.recording_team_number == recording_team
+        })
+    }
+
+    async fn get_all_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find_match(viewer, team_number, &event, |r| {
+            r.match_number == match_number
+        })
+    }
+
+    async fn get_all_team_event_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find_match(viewer, team_number, &event, |_| true)
+    }
+
+    async fn get_last_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_last_pit(viewer, team_number, &event, |_| true)
+    }
+
+    async fn get_all_team_pit_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        event: String,
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.find_pit(viewer, team_number, &event, |r| {
+            r.recording_team == recording_team
+        })
+    }
+
+    async fn get_team_pit_data_by_induvidual(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        recording_induvidual: String,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_last_pit(viewer, team_number, &event, |r| {
+            r.recording_team == recording_team && r.team_member == recording_induvidual
+        })
+    }
+
+    async fn get_last_team_pit_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_last_pit(viewer, team_number, &event, |r| {
+            r.recording_team == recording_team
+        })
+    }
+
+    async fn get_all_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.find_pit(viewer, team_number, &event, |_| true)
+    }
+
+    async fn get_key(&self, id: &str) -> Result<ApiKey> {
+        self.read(|s| s.keys.iter().find(|k| k._id == id).cloned())?
+            .ok_or(anyhow!(StatusCode::UNAUTHORIZED))
+    }
+
+    async fn get_team_keys(&self, team: u32) -> Result<Vec<ApiKey>> {
+        self.read(|s| s.keys.iter().filter(|k| k.team == team).cloned().collect())
+    }
+
+    async fn get_all_keys(&self) -> Result<Vec<ApiKey>> {
+        self.read(|s| s.keys.clone())
+    }
+
+    async fn put_key(&self, key: ApiKey) -> Result<()> {
+        self.write(|s| match s.keys.iter_mut().find(|k| k._id == key._id) {
+            Some(existing) => *existing = key,
+            None => s.keys.push(key),
+        })
+    }
+
+    async fn delete_key(&self, id: &str) -> Result<()> {
+        let found = self.write(|s| {
+            let before = s.keys.len();
+            s.keys.retain(|k| k._id != id);
+            s.keys.len() != before
+        })?;
+        if !found {
+            return Err(anyhow!(StatusCode::NOT_FOUND));
+        }
+        Ok(())
+    }
+}
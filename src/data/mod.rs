@@ -1,170 +1,670 @@
+pub mod accuracy;
+pub mod cache;
+pub mod frcevents;
+pub mod health;
+pub mod keys;
+pub mod memory;
 pub mod openscout;
-pub mod season; //data structs
+pub mod pack;
+pub mod season; //data structs, one module per game
+pub mod session;
+pub mod source;
+pub mod sqlite;
 pub mod statbotics;
+pub mod stats;
+pub mod store;
 pub mod theblueallience;
-use axum::{http::HeaderMap, response::IntoResponse};
+use axum::{
+    http::{request::Parts, HeaderMap},
+    response::IntoResponse,
+};
 use chrono::{TimeZone, Utc};
 use log::warn;
-use openscout::{Auth, AuthLevel, MongoAuth};
+use openscout::{Auth, AuthLevel, Permission};
 use rand::{prelude::Distribution, seq::IteratorRandom};
 use reqwest::StatusCode;
-use serde_json::Value;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use utoipa::ToSchema;
 
 use std::{
-    collections::{binary_heap::Iter, HashMap},
+    collections::{binary_heap::Iter, HashMap, HashSet},
+    hash::Hash,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
     thread::current,
+    time::{Duration, Instant},
 };
 
+use accuracy::{AccuracyTally, ScoutingAccuracy};
 use anyhow::*;
+use cache::HttpCache;
+use frcevents::{FrcApiAuth, FrcEvents};
+use health::{SourceHealth, SourceStatus, PROBE_INTERVAL};
+use keys::{ApiKey, IssuedKey, KeyInfo, KeyRequest, KeyUpdate, TeamAccount, VerifiedKeys};
+use pack::EventPack;
+use season::{FieldCheck, Season};
 use serde::{Deserialize, Serialize};
+use session::{Caller, LoginRequest, Session, SessionSigner};
+use source::{EventSource, EventSources, Ranking};
 use statbotics::Statbotics;
-use theblueallience::{TbaScoreBreakdown, TheBlueAllience};
+use stats::{Consensus, FieldConflict, FieldTrend, TrendPoint};
+use store::{ScoutStore, Shared, Viewer};
+use theblueallience::{TbaMatchData, TheBlueAllience};
 
-use crate::{assignments, get_team_pit_data};
+use crate::assignments::{
+    EventSchedule, MatchCoverage, MatchScoutAssignments, ScoutingAssignment, ShiftRequest,
+    ShiftSchedule,
+};
 
 //TODO: set a client here so that the connection pool is shared by all there services (or not, I
 //don't think there would be a benifit to this)
 
 #[derive(Clone)]
-pub struct DataManager {
-    openscoutdb: openscout::OpenScoutDB,
+pub struct DataManager<S: Season> {
+    store: Arc<dyn ScoutStore<S>>,
     tba: theblueallience::TheBlueAllience,
+    ///schedules and results, from TBA and/or the FRC API in the configured order
+    sources: EventSources<S>,
     statbotics: statbotics::Statbotics,
+    frc: Option<FrcEvents>,
+    source_order: Vec<String>,
+    ///every season the server hosts, oldest first
+    seasons: Vec<u32>,
+    ///which of the apis above are reachable, shared with the background probe
+    health: SourceHealth,
     event_list: Vec<String>,
     //TODO: I may want to add a flag that enables event checks. This would be for scenarios where
     //the event is not defined such as scrimiges
     enable_auth: bool,
+    verified_keys: VerifiedKeys,
+    sessions: SessionSigner,
     enable_event_check: bool,
-    global_match_assignment: HashMap<String, MatchScoutAssignments>,
-    team_match_assignments: HashMap<(u32, String), MatchScoutAssignments>,
+    //shared between every clone the handlers get
+    global_match_assignment: Arc<Mutex<HashMap<String, MatchScoutAssignments>>>,
+    team_match_assignments: Arc<Mutex<HashMap<(u32, String), MatchScoutAssignments>>>,
+    schedules: Arc<RwLock<HashMap<String, EventSchedule>>>,
+    shift_schedules: Arc<Mutex<HashMap<(u32, String), ShiftSchedule>>>,
+    ///the minimum number of scouts on every robot in the global pool
+    scouts_per_robot: u32,
+    current_matches: Arc<Mutex<HashMap<String, (Instant, CurrentMatch)>>>,
+    ///by who asked as well, everyone sees a different set of reports
+    accuracy: Arc<Mutex<HashMap<(Viewer, String), CachedAccuracy>>>,
+    ///a match that started longer ago than this is over as far as the scouts are concerned
+    dead_match_after: Duration,
+}
+
+const CURRENT_MATCH_CACHE_TIME: Duration = Duration::from_secs(15);
+///When it was worked out and what it came to
+type CachedAccuracy = (Instant, ScoutingAccuracy);
+
+///Working out the scouting accuracy looks at every report at the event so the consensus reuses it
+///for a while
+const ACCURACY_CACHE_TIME: Duration = Duration::from_secs(300);
+///Even the worst scout still counts for a bit, they could have been right this time
+const MIN_SCOUT_WEIGHT: f64 = 0.1;
+
+///Everything from the config the DataManager needs, see OSConfig in main for what each does
+#[derive(Debug, Default)]
+pub struct DataManagerOptions {
+    pub tba_key: String,
+    pub enable_auth: Option<bool>,
+    pub scouts_per_robot: Option<u32>,
+    pub dead_match_minutes: Option<u64>,
+    pub frc_api: Option<FrcApiAuth>,
+    pub source_order: Option<Vec<String>>,
+    pub event_pack: Option<PathBuf>,
+    pub seasons: Vec<u32>,
+    pub token_secret: Option<String>,
+    pub session_hours: Option<u64>,
 }
 
-impl DataManager {
-    pub async fn new(
-        tba_key: String,
-        mongo_auth: Option<MongoAuth>,
-        enable_auth: Option<bool>,
+impl<S: Season> DataManager<S> {
+    pub async fn new(options: DataManagerOptions, store: Arc<dyn ScoutStore<S>>) -> Result<Self> {
+        //shared so tba and statbotics requests all go through the same cache
+        let cache = match &options.event_pack {
+            Some(path) => HttpCache::from_pack(EventPack::load(path)?),
+            None => HttpCache::new(),
+        };
+        let health = SourceHealth::new();
+        let tba = TheBlueAllience::new(options.tba_key, cache.clone());
+        let statbotics = Statbotics::new(cache.clone());
+        let frc = match options.frc_api {
+            Some(_) if cache.is_offline() => {
+                warn!("running from an event pack, the FRC API will not be used");
+                None
+            }
+            Some(auth) => Some(FrcEvents::new(auth)),
+            None => None,
+        };
+
+        //not being able to reach an api is not a reason to not start, the venue wifi might just
+        //be bad. It gets skipped until the probe sees it again.
+        if !cache.is_offline() {
+            health.record("tba", tba.check().await);
+            health.record("statbotics", statbotics.check().await);
+            if let Some(frc) = &frc {
+                health.record("frc", frc.check().await);
+            }
+            spawn_health_probe(health.clone(), tba.clone(), statbotics.clone(), frc.clone());
+        }
+
+        //the FRC API is ahead of TBA so it goes first when there is a token for it
+        let source_order = options
+            .source_order
+            .unwrap_or(vec!["frc".to_string(), "tba".to_string()]);
+
+        Self::with_clients(
+            store,
+            tba,
+            statbotics,
+            frc,
+            source_order,
+            options.seasons,
+            health,
+            options.enable_auth.unwrap_or(true),
+            VerifiedKeys::new(),
+            SessionSigner::new(
+                options.token_secret,
+                Duration::from_secs(options.session_hours.unwrap_or(12) * 60 * 60),
+            ),
+            options.scouts_per_robot.unwrap_or(1),
+            Duration::from_secs(options.dead_match_minutes.unwrap_or(10) * 60),
+        )
+        .await
+    }
+
+    ///A DataManager for another season on the same apis, the caches and health checks are shared
+    ///with this one and so are the keys and sessions. The events and assignments are not, those are per
+    ///season.
+    pub async fn for_season<S2: Season>(
+        &self,
+        store: Arc<dyn ScoutStore<S2>>,
+    ) -> Result<DataManager<S2>> {
+        DataManager::with_clients(
+            store,
+            self.tba.clone(),
+            self.statbotics.clone(),
+            self.frc.clone(),
+            self.source_order.clone(),
+            self.seasons.clone(),
+            self.health.clone(),
+            self.enable_auth,
+            self.verified_keys.clone(),
+            self.sessions.clone(),
+            self.scouts_per_robot,
+            self.dead_match_after,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn with_clients(
+        store: Arc<dyn ScoutStore<S>>,
+        tba: TheBlueAllience,
+        statbotics: Statbotics,
+        frc: Option<FrcEvents>,
+        source_order: Vec<String>,
+        seasons: Vec<u32>,
+        health: SourceHealth,
+        enable_auth: bool,
+        verified_keys: VerifiedKeys,
+        sessions: SessionSigner,
+        scouts_per_robot: u32,
+        dead_match_after: Duration,
     ) -> Result<Self> {
-        let tba = TheBlueAllience::new(tba_key).await?;
-        let event_keys = tba.get_event_keys().await?;
+        let mut event_keys = health
+            .call("tba", tba.get_event_keys(S::YEAR))
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "unable to get the {} event list, events will not be checked: {}",
+                    S::YEAR,
+                    e
+                );
+                Vec::new()
+            });
+        //off-season events early in the next year still play this game, the next year's list is
+        //often not out yet so it is fine if it fails
+        if !event_keys.is_empty() {
+            if let Result::Ok(next) = health.call("tba", tba.get_event_keys(S::YEAR + 1)).await {
+                event_keys.extend(next);
+            }
+        }
+
+        let mut sources: Vec<Arc<dyn EventSource<S>>> = Vec::new();
+        for name in &source_order {
+            match name.as_str() {
+                "tba" => sources.push(Arc::new(tba.clone())),
+                "frc" => match &frc {
+                    Some(frc) => sources.push(Arc::new(frc.clone())),
+                    None => warn!("frc is in the source order but the FRC API is not available"),
+                },
+                other => return Err(anyhow!("unknown schedule source {}", other)),
+            }
+        }
 
         Ok(Self {
-            openscoutdb: openscout::OpenScoutDB::new(None, mongo_auth).await?,
+            store,
             tba,
-            statbotics: Statbotics::new().await?,
+            sources: EventSources::new(sources, health.clone())?,
+            statbotics,
+            frc,
+            source_order,
+            seasons,
+            health,
             event_list: event_keys,
-            enable_auth: enable_auth.unwrap_or(true),
+            enable_auth,
+            verified_keys,
+            sessions,
             enable_event_check: true, //TODO: put this in the config
-            global_match_assignment: HashMap::new(),
-            team_match_assignments: HashMap::new(),
+            global_match_assignment: Arc::new(Mutex::new(HashMap::new())),
+            team_match_assignments: Arc::new(Mutex::new(HashMap::new())),
+            schedules: Arc::new(RwLock::new(HashMap::new())),
+            shift_schedules: Arc::new(Mutex::new(HashMap::new())),
+            scouts_per_robot,
+            current_matches: Arc::new(Mutex::new(HashMap::new())),
+            accuracy: Arc::new(Mutex::new(HashMap::new())),
+            dead_match_after,
         })
     }
 
+    ///Whatever TBA and Statbotics could give, the fields from a source that failed are left empty
+    ///and `sources` says why
     pub async fn get_team_data(&self, team_number: u32, event: String) -> Result<TeamData> {
-        let tba_data = self.tba.get_team_data(team_number.clone(), event).await?;
-        let statbotics_data = self.statbotics.get_team_data(team_number.clone()).await?;
+        let (tba_data, statbotics_data) = tokio::join!(
+            self.health
+                .call("tba", self.tba.get_team_data(team_number, event)),
+            self.health.call(
+                "statbotics",
+                self.statbotics.get_team_data(team_number, S::YEAR)
+            ),
+        );
 
+        let tba = tba_data.as_ref().ok();
+        let epa = statbotics_data.as_ref().ok().map(|data| &data.epa);
         Ok(TeamData {
             team_number,
 
-            opr: tba_data.opr,
-            dpr: tba_data.dpr,
-            ccwm: tba_data.ccwm,
+            opr: tba.map(|data| data.opr),
+            dpr: tba.map(|data| data.dpr),
+            ccwm: tba.map(|data| data.ccwm),
+
+            unitless_epa: epa.map(|epa| epa.unitless),
+            norm_epa: epa.map(|epa| epa.norm),
 
-            unitless_epa: statbotics_data.epa.unitless,
-            norm_epa: statbotics_data.epa.norm,
+            sources: vec![
+                SourceStatus::of("tba", &tba_data),
+                SourceStatus::of("statbotics", &statbotics_data),
+            ],
         })
     }
 
-    pub async fn get_match_data(&self, event: String, match_num: MatchNumber) -> Result<MatchData> {
-        let tba_data = self
-            .tba
-            .get_match_data(match_num.clone(), event.clone())
-            .await?;
-        let statbotics_data = self
-            .statbotics
-            .get_match_data(event.clone(), match_num.clone())
-            .await?;
+    pub fn get_seasons(&self) -> Vec<u32> {
+        self.seasons.clone()
+    }
+
+    ///The team's EPA in each of the years, oldest first. The normalized EPA is the one to compare
+    ///across years, the unitless one depends on the game.
+    pub async fn get_team_history(&self, team_number: u32, years: Vec<u32>) -> Vec<TeamSeason> {
+        let mut seasons = Vec::new();
+        //one at a time, they are cached and statbotics does not like being hammered
+        for year in years {
+            let statbotics_data = self
+                .health
+                .call(
+                    "statbotics",
+                    self.statbotics.get_team_data(team_number, year),
+                )
+                .await;
+
+            let epa = statbotics_data.as_ref().ok().map(|data| &data.epa);
+            seasons.push(TeamSeason {
+                year,
+                unitless_epa: epa.map(|epa| epa.unitless),
+                norm_epa: epa.map(|epa| epa.norm),
+                sources: vec![SourceStatus::of("statbotics", &statbotics_data)],
+            });
+        }
+        seasons.sort_by_key(|season| season.year);
+        seasons
+    }
+
+    ///Same as get_team_data, the schedule part comes from whichever schedule source answers
+    pub async fn get_match_data(
+        &self,
+        event: String,
+        match_num: MatchNumber,
+    ) -> Result<MatchData<S>> {
+        let (schedule_data, statbotics_data) = tokio::join!(
+            self.sources
+                .get_match_data(match_num.clone(), event.clone()),
+            self.health.call(
+                "statbotics",
+                self.statbotics
+                    .get_match_data(event.clone(), match_num.clone())
+            ),
+        );
 
+        let schedule = schedule_data.as_ref().ok();
+        let pred = statbotics_data.as_ref().ok().map(|data| &data.pred);
         Ok(MatchData {
-            winner: tba_data.winning_allience,
-            predicted_winner: statbotics_data.pred.winner,
-            red_win_prob: statbotics_data.pred.red_win_prob,
+            winner: schedule.and_then(|data| data.winning_allience.clone()),
+            predicted_winner: pred.and_then(|pred| pred.winner.clone()),
+            red_win_prob: pred.map(|pred| pred.red_win_prob),
             //TODO: there appears to be a bug where the thrid alliance is 0
-            red_allience: tba_data.red_allience,
-            blue_allience: tba_data.blue_allience,
-            red_score: tba_data.red_score,
-            blue_score: tba_data.blue_score,
-            red_score_breakdown: tba_data.red_score_breakdown,
-            blue_score_breakdown: tba_data.blue_score_breakdown,
-            predicted_red_score: statbotics_data.pred.red_score,
-            predicted_blue_score: statbotics_data.pred.blue_score,
+            red_allience: schedule.map(|data| data.red_allience),
+            blue_allience: schedule.map(|data| data.blue_allience),
+            red_score: schedule.map(|data| data.red_score),
+            blue_score: schedule.map(|data| data.blue_score),
+            red_score_breakdown: schedule.and_then(|data| data.red_score_breakdown.clone()),
+            blue_score_breakdown: schedule.and_then(|data| data.blue_score_breakdown.clone()),
+            predicted_red_score: pred.map(|pred| pred.red_score),
+            predicted_blue_score: pred.map(|pred| pred.blue_score),
             event,
             match_number: match_num,
+            sources: vec![
+                SourceStatus::of(self.sources.name(), &schedule_data),
+                SourceStatus::of("statbotics", &statbotics_data),
+            ],
         })
     }
 
-    pub async fn post_team_match_data(&self, data: TeamMatchReport) -> Result<()> {
+    ///Every scouted report for the match next to TBA's per robot results for the same robot.
+    ///Errors when the match has not been played yet.
+    pub async fn check_match_reports(
+        &self,
+        viewer: Viewer,
+        event: String,
+        match_num: MatchNumber,
+    ) -> Result<Vec<ReportCheck>> {
+        let official = self
+            .sources
+            .get_match_data(match_num.clone(), event.clone())
+            .await?;
+
+        let alliances = [
+            (
+                Allience::RED,
+                official.red_allience,
+                official.red_score_breakdown,
+            ),
+            (
+                Allience::BLUE,
+                official.blue_allience,
+                official.blue_score_breakdown,
+            ),
+        ];
+
+        let mut checks = Vec::new();
+        for (alliance, teams, breakdown) in alliances {
+            let breakdown = breakdown.ok_or(anyhow!("the match has not been played yet"))?;
+
+            for (station, team_number) in teams.into_iter().enumerate() {
+                //no reports for a robot is fine, there just is nothing to check
+                let reports = none_if_no_content(
+                    self.store
+                        .get_all_team_match_data(
+                            viewer,
+                            team_number,
+                            match_num.clone(),
+                            event.clone(),
+                        )
+                        .await,
+                )?
+                .unwrap_or_default();
+
+                for report in reports {
+                    checks.push(ReportCheck {
+                        team_number,
+                        alliance: alliance.clone(),
+                        station: station as u32 + 1,
+                        recording_team_number: report.recording_team_number,
+                        team_member: report.team_member,
+                        timestamp: report.timestamp,
+                        checks: S::check_robot(&report.data, &breakdown, station),
+                    });
+                }
+            }
+        }
+
+        Ok(checks)
+    }
+
+    ///How close every scout's reports at the event got to TBA's score breakdowns, see accuracy.rs.
+    ///Always TBA since the other sources do not have the per robot results.
+    pub async fn get_scouting_accuracy(
+        &self,
+        viewer: Viewer,
+        event: String,
+    ) -> Result<ScoutingAccuracy> {
+        self.check_event_key(&event)?;
+        let matches = self
+            .health
+            .call("tba", self.tba.get_match_data_list::<S>(event.clone()))
+            .await?;
+
+        //one lookup per team instead of one per robot per match
+        let teams: HashSet<u32> = matches
+            .iter()
+            .flat_map(|m| m.red_allience.into_iter().chain(m.blue_allience))
+            .collect();
+        let mut reports: HashMap<(u32, MatchNumber), Vec<TeamMatchReport<S>>> = HashMap::new();
+        for team in teams {
+            let team_reports = none_if_no_content(
+                self.store
+                    .get_all_team_event_match_data(viewer, team, event.clone())
+                    .await,
+            )?
+            .unwrap_or_default();
+            for report in team_reports {
+                reports
+                    .entry((team, report.match_number.clone()))
+                    .or_default()
+                    .push(report);
+            }
+        }
+
+        let mut tally = AccuracyTally::new();
+        for played in matches {
+            let mut take = |teams: [u32; 3]| {
+                teams.map(|team| {
+                    reports
+                        .remove(&(team, played.match_number.clone()))
+                        .unwrap_or_default()
+                })
+            };
+            let alliances: Vec<_> = [
+                (played.red_allience, played.red_score_breakdown),
+                (played.blue_allience, played.blue_score_breakdown),
+            ]
+            .into_iter()
+            //no breakdown means it has not been played
+            .filter_map(|(teams, breakdown)| Some((take(teams), breakdown?)))
+            .collect();
+            tally.add_match::<S>(&alliances);
+        }
+
+        Ok(tally.finish(event))
+    }
+
+    ///Merges every report on the robot in the match into one. Each scout counts as much as their
+    ///scouting accuracy at the event, or the same as everyone else when that can not be worked
+    ///out (nothing played yet, TBA is down).
+    pub async fn get_match_consensus(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<MatchConsensus<S>> {
+        let reports = self
+            .store
+            .get_all_team_match_data(viewer, team_number, match_number.clone(), event.clone())
+            .await?;
+
+        let accuracy = self.cached_accuracy(viewer, &event).await;
+        let weights: Vec<f64> = reports
+            .iter()
+            .map(|r| {
+                accuracy
+                    .as_ref()
+                    .and_then(|a| a.weight(r.recording_team_number, &r.team_member))
+                    .unwrap_or(1.0)
+                    .max(MIN_SCOUT_WEIGHT)
+            })
+            .collect();
+        let data: Vec<S::MatchData> = reports.iter().map(|r| r.data.clone()).collect();
+
+        let mut consensus = Consensus::new(&data, &weights);
+        let merged = S::match_consensus(&mut consensus);
+
+        Ok(MatchConsensus {
+            team_number,
+            event,
+            match_number,
+            data: merged,
+            confidence: consensus.confidence(),
+            outliers: consensus
+                .outliers()
+                .iter()
+                .map(|outlier| ConsensusOutlier {
+                    recording_team_number: reports[outlier.report].recording_team_number,
+                    team_member: reports[outlier.report].team_member.clone(),
+                    field: outlier.field.clone(),
+                    value: outlier.value,
+                    consensus: outlier.consensus,
+                })
+                .collect(),
+            scouts: reports
+                .iter()
+                .zip(&weights)
+                .map(|(r, weight)| ConsensusScout {
+                    recording_team_number: r.recording_team_number,
+                    team_member: r.team_member.clone(),
+                    weight: *weight,
+                })
+                .collect(),
+            notes: reports
+                .iter()
+                .map(|r| r.notes.clone())
+                .filter(|notes| !notes.is_empty())
+                .collect(),
+        })
+    }
+
+    async fn cached_accuracy(&self, viewer: Viewer, event: &String) -> Option<ScoutingAccuracy> {
+        let cached = self
+            .accuracy
+            .lock()
+            .ok()?
+            .get(&(viewer, event.clone()))
+            .filter(|(fetched, _)| fetched.elapsed() < ACCURACY_CACHE_TIME)
+            .map(|(_, accuracy)| accuracy.clone());
+        if cached.is_some() {
+            return cached;
+        }
+
+        match self.get_scouting_accuracy(viewer, event.clone()).await {
+            Result::Ok(accuracy) => {
+                self.accuracy
+                    .lock()
+                    .ok()?
+                    .insert((viewer, event.clone()), (Instant::now(), accuracy.clone()));
+                Some(accuracy)
+            }
+            Err(e) => {
+                warn!(
+                    "unable to work out the scouting accuracy for {}, every scout counts the same: {}",
+                    event, e
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn post_team_match_data(&self, data: TeamMatchReport<S>) -> Result<()> {
         self.check_event_key(&data.event)?;
-        self.openscoutdb.post_team_match_data(data).await?;
+        self.store.post_team_match_data(data).await?;
         Ok(())
     }
 
-    pub async fn post_team_pit_data(&self, data: TeamPitReport) -> Result<()> {
+    pub async fn post_team_pit_data(&self, data: TeamPitReport<S>) -> Result<()> {
         self.check_event_key(&data.event)?;
-        self.openscoutdb.post_team_pit_data(data).await?;
+        self.store.post_team_pit_data(data).await?;
         Ok(())
     }
 
     ///Gives the last recorded team match report
     pub async fn get_last_team_match_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TeamMatchReport> {
-        self.openscoutdb
-            .get_last_team_match_data(team_number, match_number, event)
+    ) -> Result<TeamMatchReport<S>> {
+        self.store
+            .get_last_team_match_data(viewer, team_number, match_number, event)
             .await
     }
 
-    ///
+    ///Aggregates every report for the team at the event, or only the reports for one match when
+    ///a match number is given
     pub async fn get_avg_team_match_data(
         &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: Option<MatchNumber>,
+        event: String,
+    ) -> Result<TeamMatchReportAvg<S>> {
+        self.store
+            .get_avg_team_match_data(viewer, team_number, match_number, event)
+            .await
+    }
+
+    ///Every report for the team at the event, across all matches
+    pub async fn get_all_team_event_match_data(
+        &self,
+        viewer: Viewer,
         team_number: u32,
-        match_number: MatchNumber,
         event: String,
-    ) {
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.store
+            .get_all_team_event_match_data(viewer, team_number, event)
+            .await
     }
 
     pub async fn get_all_team_match_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         match_number: MatchNumber,
         event: String,
-    ) {
-        self.openscoutdb.get_all_team_match_data_by_team(
-            team_number,
-            recording_team,
-            match_number,
-            event,
-        );
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.store
+            .get_all_team_match_data_by_team(
+                viewer,
+                team_number,
+                recording_team,
+                match_number,
+                event,
+            )
+            .await
     }
 
     pub async fn get_team_match_data_by_induvidual(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         recording_induvidual: String,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TeamMatchReport> {
-        self.openscoutdb
+    ) -> Result<TeamMatchReport<S>> {
+        self.store
             .get_team_match_data_by_induvidual(
+                viewer,
                 team_number,
                 recording_team,
                 recording_induvidual,
@@ -176,68 +676,81 @@ impl DataManager {
 
     pub async fn get_last_team_match_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TeamMatchReport> {
-        self.openscoutdb
-            .get_last_team_match_data_by_team(team_number, recording_team, match_number, event)
+    ) -> Result<TeamMatchReport<S>> {
+        self.store
+            .get_last_team_match_data_by_team(
+                viewer,
+                team_number,
+                recording_team,
+                match_number,
+                event,
+            )
             .await
     }
 
     pub async fn get_all_team_match_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<Vec<TeamMatchReport>> {
-        self.openscoutdb
-            .get_all_team_match_data(team_number, match_number, event)
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.store
+            .get_all_team_match_data(viewer, team_number, match_number, event)
             .await
     }
 
     pub async fn get_last_team_pit_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         event: String,
-    ) -> Result<TeamPitReport> {
-        self.openscoutdb
-            .get_last_team_pit_data(team_number, event)
+    ) -> Result<TeamPitReport<S>> {
+        self.store
+            .get_last_team_pit_data(viewer, team_number, event)
             .await
     }
 
-    ///
+    ///Merges every pit report for the team by majority vote, see TeamPitReport::consensus
     pub async fn get_avg_team_pit_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         event: String,
-    ) -> Result<TeamPitReport> {
-        self.openscoutdb
-            .get_avg_team_pit_data(team_number, event)
+    ) -> Result<PitConsensus<S>> {
+        self.store
+            .get_avg_team_pit_data(viewer, team_number, event)
             .await
     }
 
     pub async fn get_all_team_pit_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         event: String,
-    ) -> Result<Vec<TeamPitReport>> {
-        self.openscoutdb
-            .get_all_team_pit_data_by_team(team_number, recording_team, event)
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.store
+            .get_all_team_pit_data_by_team(viewer, team_number, recording_team, event)
             .await
     }
 
     pub async fn get_team_pit_data_by_induvidual(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         recording_induvidual: String,
         event: String,
-    ) -> Result<TeamPitReport> {
-        self.openscoutdb
+    ) -> Result<TeamPitReport<S>> {
+        self.store
             .get_team_pit_data_by_induvidual(
+                viewer,
                 team_number,
                 recording_team,
                 recording_induvidual,
@@ -248,130 +761,903 @@ impl DataManager {
 
     pub async fn get_last_team_pit_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         event: String,
-    ) -> Result<TeamPitReport> {
-        self.openscoutdb
-            .get_last_team_pit_data_by_team(team_number, recording_team, event)
+    ) -> Result<TeamPitReport<S>> {
+        self.store
+            .get_last_team_pit_data_by_team(viewer, team_number, recording_team, event)
             .await
     }
 
     pub async fn get_all_team_pit_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         event: String,
-    ) -> Result<Vec<TeamPitReport>> {
-        self.openscoutdb
-            .get_all_team_pit_data(team_number, event)
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.store
+            .get_all_team_pit_data(viewer, team_number, event)
             .await
     }
 
+    ///Everything known about a team at an event, meant to be looked over before alliance selection
+    pub async fn get_team_profile(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<TeamProfile<S>> {
+        let reports = none_if_no_content(
+            self.store
+                .get_all_team_event_match_data(viewer, team_number, event.clone())
+                .await,
+        )?
+        .unwrap_or_default();
+
+        let latest_pit_report = none_if_no_content(
+            self.store
+                .get_last_team_pit_data(viewer, team_number, event.clone())
+                .await,
+        )?;
+
+        let team_data = self.get_team_data(team_number, event.clone()).await?;
+
+        //several scouts can report on the same match, those get averaged into one point
+        let mut per_match: Vec<(MatchNumber, Vec<&TeamMatchReport<S>>)> = Vec::new();
+        for report in reports.iter() {
+            match per_match
+                .iter_mut()
+                .find(|(m, _)| *m == report.match_number)
+            {
+                Some((_, match_reports)) => match_reports.push(report),
+                None => per_match.push((report.match_number.clone(), vec![report])),
+            }
+        }
+        per_match.sort_by(|(a, _), (b, _)| (&a.level, a.number).cmp(&(&b.level, b.number)));
+
+        let mut points: HashMap<String, Vec<TrendPoint>> = HashMap::new();
+        for (match_number, match_reports) in per_match {
+            let mut sums: HashMap<&'static str, f64> = HashMap::new();
+            for report in match_reports.iter() {
+                for (field, value) in S::numeric_fields(&report.data) {
+                    *sums.entry(field).or_insert(0.0) += value;
+                }
+            }
+
+            for (field, sum) in sums {
+                points
+                    .entry(field.to_string())
+                    .or_default()
+                    .push(TrendPoint {
+                        match_number: match_number.clone(),
+                        value: sum / match_reports.len() as f64,
+                    });
+            }
+        }
+
+        Ok(TeamProfile {
+            team_number,
+            event,
+            match_data: match reports.is_empty() {
+                true => None,
+                false => Some(TeamMatchReport::avg(reports)?),
+            },
+            trends: points
+                .into_iter()
+                .map(|(field, points)| (field, FieldTrend::new(points)))
+                .collect(),
+            latest_pit_report,
+            team_data,
+        })
+    }
+
     pub async fn get_event_data(&self) -> Result<Vec<Eventdata>> {
-        self.tba.get_event_list().await
+        self.tba.get_event_list(S::YEAR).await
     }
 
-    pub async fn check_auth(&self, headers: &HeaderMap, required_auth: AuthLevel) -> Result<()> {
+    ///The caller if their role has `permission`, none when auth is off. See session::Authorized
+    ///for using this in a handler.
+    pub async fn authorize(&self, parts: &Parts, permission: Permission) -> Result<Option<Caller>> {
+        let caller = self.authenticate(parts).await?;
+        if let Some(caller) = &caller {
+            //they are who they say, they just can not do this
+            if !caller.auth.allows(permission) {
+                return Err(anyhow!(StatusCode::FORBIDDEN));
+            }
+        }
+        Ok(caller)
+    }
+
+    async fn authenticate(&self, parts: &Parts) -> Result<Option<Caller>> {
         if !self.enable_auth {
-            return Ok(());
+            return Ok(None);
+        }
+
+        if let Some(token) = session::bearer_token(parts) {
+            let claims = self.sessions.verify(token)?;
+            //the key is looked up again so revoking or rotating it ends its sessions too
+            let key = self.store.get_key(&claims.kid).await?;
+            if key.team != claims.team || key.created != claims.key_created || !key.usable() {
+                return Err(anyhow!(StatusCode::UNAUTHORIZED));
+            }
+            return Ok(Some(key.caller()));
         }
 
         //it is the destiny of all my codebases to have some annoying ugly as crap code to convert
         //things to the correct datatype
-        //TODO: give this actual errors
-        let team: u32 = headers
-            .get("id")
-            .unwrap_or(return Err(anyhow!(StatusCode::BAD_REQUEST)))
-            .to_str()?
-            .parse()?;
-        let key: String = headers
+        //no token and no id means no credentials at all
+        let team = caller_team(&parts.headers).map_err(|_| anyhow!(StatusCode::UNAUTHORIZED))?;
+        let presented = parts
+            .headers
             .get("key")
-            .unwrap_or(return Err(anyhow!(StatusCode::BAD_REQUEST)))
-            .to_str()?
-            .to_string();
+            .ok_or(anyhow!(StatusCode::UNAUTHORIZED))?
+            .to_str()?;
+        Ok(Some(self.verify_key(team, presented).await?.caller()))
+    }
 
-        let auth = self.openscoutdb.check_auth(team).await?;
+    async fn verify_key(&self, team: u32, presented: &str) -> Result<ApiKey> {
+        let (id, secret) = keys::split_key(team, presented);
 
-        if key != auth.key || auth.auth < required_auth {
+        let key = match self.verified_keys.get(&id, secret) {
+            Some(key) => key,
+            None => {
+                let key = self.store.get_key(&id).await?;
+                let checked = key.clone();
+                let given = secret.to_string();
+                if !tokio::task::spawn_blocking(move || checked.verify(&given)).await? {
+                    return Err(anyhow!(StatusCode::UNAUTHORIZED));
+                }
+                self.verified_keys.insert(secret, key.clone());
+                key
+            }
+        };
+
+        if key.team != team || !key.usable() {
             return Err(anyhow!(StatusCode::UNAUTHORIZED));
         }
+        Ok(key)
+    }
 
+    ///Trades a key for a bearer token
+    pub async fn login(&self, request: LoginRequest) -> Result<Session> {
+        let key = self.verify_key(request.team, &request.key).await?;
+        self.sessions.sign(&key.caller(), key.created)
+    }
+
+    ///Keys are managed by an admin or by the team they belong to. Gives back the caller's role,
+    ///it decides what keys they can hand out.
+    pub fn check_key_access(&self, caller: Option<&Caller>, team: u32) -> Result<AuthLevel> {
+        match caller {
+            None => Ok(AuthLevel::ADMIN),
+            Some(caller) if caller.auth.allows(Permission::ManageServer) || caller.team == team => {
+                Ok(caller.auth.clone())
+            }
+            Some(_) => Err(anyhow!(StatusCode::FORBIDDEN)),
+        }
+    }
+
+    ///`granter` is the role of whoever asked for the key, see check_key_access
+    pub async fn issue_key(
+        &self,
+        team: u32,
+        request: KeyRequest,
+        granter: AuthLevel,
+    ) -> Result<IssuedKey> {
+        if let Some(auth) = &request.auth {
+            if !granter.can_grant(auth) {
+                return Err(anyhow!(StatusCode::FORBIDDEN));
+            }
+        }
+        self.check_enabled(team).await?;
+        let (key, presented) =
+            tokio::task::spawn_blocking(move || ApiKey::issue(team, request)).await??;
+        let info = key.info();
+        self.store.put_key(key).await?;
+        Ok(IssuedKey {
+            key: presented,
+            info,
+        })
+    }
+
+    pub async fn get_keys(&self, team: u32) -> Result<Vec<KeyInfo>> {
+        Ok(self
+            .store
+            .get_team_keys(team)
+            .await?
+            .iter()
+            .map(ApiKey::info)
+            .collect())
+    }
+
+    ///Stops a key from working straight away, for when a tablet goes missing
+    pub async fn revoke_key(&self, team: u32, id: String, granter: AuthLevel) -> Result<KeyInfo> {
+        let mut key = self.managed_key(team, &id, &granter).await?;
+        key.revoked = true;
+        let info = key.info();
+        self.store.put_key(key).await?;
+        self.verified_keys.forget(&id);
+        Ok(info)
+    }
+
+    ///A new secret for the key, the old one stops working straight away
+    pub async fn rotate_key(&self, team: u32, id: String, granter: AuthLevel) -> Result<IssuedKey> {
+        let key = self.managed_key(team, &id, &granter).await?;
+        self.rotate(key).await
+    }
+
+    async fn rotate(&self, mut key: ApiKey) -> Result<IssuedKey> {
+        let id = key._id.clone();
+        if key.revoked {
+            return Err(anyhow!(StatusCode::CONFLICT)
+                .context(format!("key {} is revoked, issue a new one", id)));
+        }
+        let (key, presented) = tokio::task::spawn_blocking(move || {
+            let presented = key.rotate()?;
+            Ok((key, presented))
+        })
+        .await??;
+        let info = key.info();
+        self.store.put_key(key).await?;
+        self.verified_keys.forget(&id);
+        Ok(IssuedKey {
+            key: presented,
+            info,
+        })
+    }
+
+    ///Changes the key's name, role or expiry. A role change is seen on the next request, even by
+    ///the key's bearer tokens.
+    pub async fn update_key(
+        &self,
+        team: u32,
+        id: String,
+        update: KeyUpdate,
+        granter: AuthLevel,
+    ) -> Result<KeyInfo> {
+        let mut key = self.managed_key(team, &id, &granter).await?;
+        if let Some(auth) = &update.auth {
+            if !granter.can_grant(auth) {
+                return Err(anyhow!(StatusCode::FORBIDDEN));
+            }
+        }
+        key.update(update);
+        let info = key.info();
+        self.store.put_key(key).await?;
+        self.verified_keys.forget(&id);
+        Ok(info)
+    }
+
+    ///Gone for good, revoking keeps the key around so it still shows up in the list
+    pub async fn delete_key(&self, team: u32, id: String, granter: AuthLevel) -> Result<()> {
+        self.managed_key(team, &id, &granter).await?;
+        self.store.delete_key(&id).await?;
+        self.verified_keys.forget(&id);
+        Ok(())
+    }
+
+    ///A new secret for the key the caller is using, whatever their role. Their old tokens stop
+    ///working with it.
+    pub async fn change_own_key(&self, caller: Option<Caller>) -> Result<IssuedKey> {
+        //with auth off there is no key to change
+        let caller = caller.ok_or(
+            anyhow!(StatusCode::BAD_REQUEST).context("auth is off, there is no key to change"),
+        )?;
+        //no grant check, a scout can not hand out a SCOUT key but can change their own
+        let key = self.store.get_key(&caller.key_id).await?;
+        self.rotate(key).await
+    }
+
+    ///Every team that has a key, lowest number first
+    pub async fn get_teams(&self) -> Result<Vec<TeamAccount>> {
+        let mut teams: Vec<TeamAccount> = Vec::new();
+        for key in self.store.get_all_keys().await? {
+            match teams.iter_mut().find(|t| t.team == key.team) {
+                Some(account) => {
+                    account.disabled |= key.disabled;
+                    account.keys.push(key.info());
+                }
+                None => teams.push(TeamAccount {
+                    team: key.team,
+                    disabled: key.disabled,
+                    keys: vec![key.info()],
+                }),
+            }
+        }
+        teams.sort_by_key(|t| t.team);
+        Ok(teams)
+    }
+
+    ///Turns every key the team has off or back on. Their reports stay and keep counting, they
+    ///just can not get in.
+    pub async fn set_team_disabled(&self, team: u32, disabled: bool) -> Result<TeamAccount> {
+        let keys = self.store.get_team_keys(team).await?;
+        if keys.is_empty() {
+            return Err(
+                anyhow!(StatusCode::NOT_FOUND).context(format!("team {} has no keys", team))
+            );
+        }
+        let mut infos = Vec::new();
+        for mut key in keys {
+            key.disabled = disabled;
+            infos.push(key.info());
+            let id = key._id.clone();
+            self.store.put_key(key).await?;
+            self.verified_keys.forget(&id);
+        }
+        Ok(TeamAccount {
+            team,
+            disabled,
+            keys: infos,
+        })
+    }
+
+    ///Takes away every key the team has, the reports they posted are kept
+    pub async fn delete_team(&self, team: u32) -> Result<()> {
+        let keys = self.store.get_team_keys(team).await?;
+        if keys.is_empty() {
+            return Err(
+                anyhow!(StatusCode::NOT_FOUND).context(format!("team {} has no keys", team))
+            );
+        }
+        for key in keys {
+            self.store.delete_key(&key._id).await?;
+            self.verified_keys.forget(&key._id);
+        }
+        Ok(())
+    }
+
+    ///A key of the team's the granter is allowed to touch, a team lead can not change an admin
+    ///key even when it is on their team
+    async fn managed_key(&self, team: u32, id: &str, granter: &AuthLevel) -> Result<ApiKey> {
+        let key = match self.store.get_key(id).await {
+            Result::Ok(key) if key.team == team => key,
+            _ => {
+                return Err(anyhow!(StatusCode::NOT_FOUND)
+                    .context(format!("team {} has no key {}", team, id)))
+            }
+        };
+        if !granter.can_grant(&key.auth) {
+            return Err(anyhow!(StatusCode::FORBIDDEN));
+        }
+        Ok(key)
+    }
+
+    ///New keys for a disabled team would get around it being disabled
+    async fn check_enabled(&self, team: u32) -> Result<()> {
+        if self
+            .store
+            .get_team_keys(team)
+            .await?
+            .iter()
+            .any(|key| key.disabled)
+        {
+            return Err(anyhow!(StatusCode::CONFLICT).context(format!("team {} is disabled", team)));
+        }
         Ok(())
     }
 
     ///This will be used on methods that write to the database to prevent data being uploaded with
     ///a nonexistant event (typos happen).
     fn check_event_key(&self, key: &String) -> Result<()> {
-        if !self.event_list.iter().any(|k| *key == *k) && self.enable_event_check {
+        //an empty list means TBA could not be reached at startup, nothing to check against then
+        if !self.event_list.is_empty()
+            && !self.event_list.iter().any(|k| *key == *k)
+            && self.enable_event_check
+        {
             return Err(anyhow!("The given event does not exist"));
         }
         Ok(())
     }
 
+    ///Sets the team's default key, the one that is looked up by the team number. Replaces the
+    ///key if the team already has one.
     pub async fn add_user(&self, auth: Auth) -> Result<()> {
-        self.openscoutdb.add_auth(auth).await?;
+        self.check_enabled(auth._id).await?;
+        let id = auth._id.to_string();
+        let key = tokio::task::spawn_blocking(move || ApiKey::from_legacy(auth)).await??;
+        self.store.put_key(key).await?;
+        self.verified_keys.forget(&id);
         Ok(())
     }
 
-    pub async fn get_current_match(&self, event: String) -> Result<MatchNumber> {
-        todo!()
+    pub async fn get_rankings(&self, event: String) -> Result<Vec<Ranking>> {
+        self.check_event_key(&event)?;
+        self.sources.get_rankings(event).await
+    }
+
+    pub async fn get_team_list(&self, event: String) -> Result<Vec<u32>> {
+        self.check_event_key(&event)?;
+        self.sources.get_team_list(event).await
+    }
+
+    ///Works out what is on the field right now from the TBA schedule.
+    ///The answer is kept for 15 seconds so a room full of tablets advancing at once does not get
+    ///us rate limited.
+    pub async fn get_current_match(&self, event: String) -> Result<CurrentMatch> {
+        let cached = self
+            .current_matches
+            .lock()
+            .map_err(|_| anyhow!("the current match lock is poisoned"))?
+            .get(&event)
+            .filter(|(fetched, _)| fetched.elapsed() < CURRENT_MATCH_CACHE_TIME)
+            .map(|(_, current)| current.clone());
+        if let Some(current) = cached {
+            return Ok(current);
+        }
+
+        self.check_event_key(&event)?;
+        let current = CurrentMatch::new(
+            event.clone(),
+            self.sources.get_match_data_list(event.clone()).await?,
+            Utc::now().timestamp() as u64,
+            self.dead_match_after,
+        );
+
+        self.current_matches
+            .lock()
+            .map_err(|_| anyhow!("the current match lock is poisoned"))?
+            .insert(event, (Instant::now(), current.clone()));
+        Ok(current)
+    }
+
+    ///The six teams in a qualifier, red 1-3 then blue 1-3.
+    ///The schedule is pulled from TBA the first time an event is asked for and again whenever a
+    ///match is missing from it since schedules tend to get published the morning of.
+    async fn get_scheduled_teams(&self, event: &str, match_num: u32) -> Result<[u32; 6]> {
+        let cached = self
+            .schedules
+            .read()
+            .map_err(|_| anyhow!("the schedule lock is poisoned"))?
+            .get(event)
+            .and_then(|schedule| schedule.teams(match_num).copied());
+        if let Some(teams) = cached {
+            return Ok(teams);
+        }
+
+        let schedule =
+            EventSchedule::new(self.sources.get_match_data_list(event.to_string()).await?);
+        let teams = schedule.teams(match_num).copied();
+        self.schedules
+            .write()
+            .map_err(|_| anyhow!("the schedule lock is poisoned"))?
+            .insert(event.to_string(), schedule);
+
+        teams.ok_or(anyhow!(
+            "qualifier {} is not on the schedule for {}",
+            match_num,
+            event
+        ))
+    }
+
+    ///The whole qualification schedule, fetched again if TBA did not have one last time
+    async fn get_event_schedule(&self, event: &str) -> Result<EventSchedule> {
+        let cached = self
+            .schedules
+            .read()
+            .map_err(|_| anyhow!("the schedule lock is poisoned"))?
+            .get(event)
+            .filter(|schedule| !schedule.is_empty())
+            .cloned();
+        if let Some(schedule) = cached {
+            return Ok(schedule);
+        }
+
+        let schedule =
+            EventSchedule::new(self.sources.get_match_data_list(event.to_string()).await?);
+        self.schedules
+            .write()
+            .map_err(|_| anyhow!("the schedule lock is poisoned"))?
+            .insert(event.to_string(), schedule.clone());
+
+        if schedule.is_empty() {
+            return Err(anyhow!("the schedule for {} is not out yet", event));
+        }
+        Ok(schedule)
     }
-    pub async fn get_global_scouting_assignment(event: String) {}
 
+    ///Gives a scout from `team_number` a robot to watch in the qualifier. The team's scouts cover
+    ///all six robots before anyone gets doubled up.
+    ///When the team has a shift schedule the scout gets the robot from their shift instead.
     pub async fn get_team_scouting_assignment(
-        &mut self,
+        &self,
         event: String,
         team_number: u32,
+        match_num: u32,
+        scout: String,
     ) -> Result<ScoutingAssignment> {
-        let mut assignments = match self
-            .team_match_assignments
-            .get_mut(&(team_number, event.clone()))
-        {
-            Some(data) => data,
-            None => {
-                self.team_match_assignments
-                    .insert((team_number, event.clone()), MatchScoutAssignments::new());
-                self.team_match_assignments
-                    .get_mut(&(team_number, event.clone()))
-                    .expect("I litterally just created this entry")
-            }
-        };
+        //none when the team has no schedule, some(none) when the scout is off
+        let shift = self.with_shift_schedule(team_number, &event, |schedule| {
+            schedule.map(|schedule| schedule.shift_for(&scout, match_num).cloned())
+        })?;
+        if let Some(shift) = shift {
+            let shift = shift.ok_or(anyhow!(
+                "{} is not on shift for qualifier {}",
+                scout,
+                match_num
+            ))?;
+            return Ok(ScoutingAssignment {
+                event,
+                match_number: MatchNumber {
+                    number: match_num,
+                    level: Complevel::Qualifier,
+                },
+                slot: shift.slot,
+                team_number: shift.team_number,
+            });
+        }
+
+        self.assign(
+            &self.team_match_assignments,
+            (team_number, event.clone()),
+            event,
+            match_num,
+            &scout,
+        )
+        .await
+    }
 
-        todo!()
+    ///Same as get_team_scouting_assignment but every team at the event draws from one pool, so a
+    ///few small scouting teams together can cover every robot. Scouts are told apart by their
+    ///team as well as their name.
+    pub async fn get_global_scouting_assignment(
+        &self,
+        event: String,
+        team_number: u32,
+        match_num: u32,
+        scout: String,
+    ) -> Result<ScoutingAssignment> {
+        self.assign(
+            &self.global_match_assignment,
+            event.clone(),
+            event,
+            match_num,
+            &format!("{}/{}", team_number, scout),
+        )
+        .await
     }
+
+    ///How many scouts the global pool has on each robot in the qualifier
+    pub fn get_global_match_coverage(
+        &self,
+        event: String,
+        match_num: u32,
+    ) -> Result<MatchCoverage> {
+        let scouts = self
+            .global_match_assignment
+            .lock()
+            .map_err(|_| anyhow!("the assignment lock is poisoned"))?
+            .get(&event)
+            .map(|pool| pool.coverage(match_num))
+            .unwrap_or_else(|| MatchScoutAssignments::default().coverage(match_num));
+
+        Ok(MatchCoverage {
+            event,
+            match_number: MatchNumber {
+                number: match_num,
+                level: Complevel::Qualifier,
+            },
+            covered: scouts.values().all(|count| *count >= self.scouts_per_robot),
+            scouts,
+            scouts_per_robot: self.scouts_per_robot,
+        })
+    }
+
+    ///Makes a shift schedule for the team covering every qualifier, this replaces any schedule the
+    ///team already had
+    pub async fn set_shift_schedule(
+        &self,
+        event: String,
+        team_number: u32,
+        request: ShiftRequest,
+    ) -> Result<ShiftSchedule> {
+        self.check_event_key(&event)?;
+        let schedule = self.get_event_schedule(&event).await?;
+        let shifts = ShiftSchedule::new(event.clone(), team_number, request, &schedule)?;
+
+        self.shift_schedules
+            .lock()
+            .map_err(|_| anyhow!("the shift schedule lock is poisoned"))?
+            .insert((team_number, event), shifts.clone());
+        Ok(shifts)
+    }
+
+    pub fn get_shift_schedule(&self, event: String, team_number: u32) -> Result<ShiftSchedule> {
+        self.with_shift_schedule(team_number, &event, |schedule| schedule.cloned())?
+            .ok_or(anyhow!(StatusCode::NO_CONTENT))
+    }
+
+    ///Puts a late scout into the rotation from `match_num` on
+    pub async fn shift_check_in(
+        &self,
+        event: String,
+        team_number: u32,
+        scout: String,
+        match_num: u32,
+    ) -> Result<ShiftSchedule> {
+        let schedule = self.get_event_schedule(&event).await?;
+        self.with_shift_schedule_mut(team_number, &event, |shifts| {
+            shifts.check_in(scout, match_num, &schedule)?;
+            Ok(shifts.clone())
+        })
+    }
+
+    ///Takes a scout that left out of the rotation from `match_num` on
+    pub async fn shift_leave(
+        &self,
+        event: String,
+        team_number: u32,
+        scout: String,
+        match_num: u32,
+    ) -> Result<ShiftSchedule> {
+        let schedule = self.get_event_schedule(&event).await?;
+        self.with_shift_schedule_mut(team_number, &event, |shifts| {
+            shifts.leave(&scout, match_num, &schedule)?;
+            Ok(shifts.clone())
+        })
+    }
+
+    fn with_shift_schedule<T>(
+        &self,
+        team_number: u32,
+        event: &str,
+        f: impl FnOnce(Option<&ShiftSchedule>) -> T,
+    ) -> Result<T> {
+        let schedules = self
+            .shift_schedules
+            .lock()
+            .map_err(|_| anyhow!("the shift schedule lock is poisoned"))?;
+        Ok(f(schedules.get(&(team_number, event.to_string()))))
+    }
+
+    fn with_shift_schedule_mut<T>(
+        &self,
+        team_number: u32,
+        event: &str,
+        f: impl FnOnce(&mut ShiftSchedule) -> Result<T>,
+    ) -> Result<T> {
+        let mut schedules = self
+            .shift_schedules
+            .lock()
+            .map_err(|_| anyhow!("the shift schedule lock is poisoned"))?;
+        let shifts = schedules
+            .get_mut(&(team_number, event.to_string()))
+            .ok_or(anyhow!(
+                "team {} has no shift schedule for {}",
+                team_number,
+                event
+            ))?;
+        f(shifts)
+    }
+
+    async fn assign<K: Eq + Hash>(
+        &self,
+        pool: &Mutex<HashMap<K, MatchScoutAssignments>>,
+        key: K,
+        event: String,
+        match_num: u32,
+        scout: &str,
+    ) -> Result<ScoutingAssignment> {
+        self.check_event_key(&event)?;
+        let teams = self.get_scheduled_teams(&event, match_num).await?;
+
+        let slot = pool
+            .lock()
+            .map_err(|_| anyhow!("the assignment lock is poisoned"))?
+            .entry(key)
+            .or_default()
+            .get_assignment(match_num, scout);
+
+        Ok(ScoutingAssignment {
+            event,
+            match_number: MatchNumber {
+                number: match_num,
+                level: Complevel::Qualifier,
+            },
+            slot,
+            team_number: slot.team(&teams),
+        })
+    }
+}
+
+///The team number a request was made as, from the `id` header
+pub fn caller_team(headers: &HeaderMap) -> Result<u32> {
+    Ok(headers
+        .get("id")
+        .ok_or(anyhow!(StatusCode::BAD_REQUEST))?
+        .to_str()?
+        .parse()?)
+}
+
+///Turns the `StatusCode::NO_CONTENT` the stores give for an empty lookup into `None`
+fn none_if_no_content<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Result::Ok(data) => Ok(Some(data)),
+        Err(e) if e.downcast_ref::<StatusCode>() == Some(&StatusCode::NO_CONTENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+///Everything about one team at one event, see DataManager::get_team_profile
+#[derive(Serialize, ToSchema)]
+#[serde(bound = "")]
+pub struct TeamProfile<S: Season> {
+    team_number: u32,
+    event: String,
+
+    ///the aggregate of every scouted match, none if the team has not been scouted yet
+    match_data: Option<TeamMatchReportAvg<S>>,
+    ///every numeric field over the matches in the order they were played
+    trends: HashMap<String, FieldTrend>,
+    latest_pit_report: Option<TeamPitReport<S>>,
+
+    ///opr and epa from tba and statbotics
+    team_data: TeamData,
 }
 
 #[derive(Debug, Serialize, ToSchema, Deserialize)]
 pub struct TeamData {
     team_number: u32,
 
-    opr: f64,
-    dpr: f64,
-    ccwm: f64,
+    //each of these is empty when the source it comes from failed
+    opr: Option<f64>,
+    dpr: Option<f64>,
+    ccwm: Option<f64>,
+
+    unitless_epa: Option<f64>,
+    norm_epa: Option<f64>,
+
+    sources: Vec<SourceStatus>,
+}
+
+///One scouted report checked against the official results, see DataManager::check_match_reports
+#[derive(Debug, Serialize, ToSchema, Deserialize)]
+pub struct ReportCheck {
+    team_number: u32,
+    alliance: Allience,
+    ///the robot's driver station, 1 to 3
+    station: u32,
+    recording_team_number: u32,
+    team_member: String,
+    timestamp: u64,
+    ///empty when the source the results came from has no per robot results
+    checks: Vec<FieldCheck>,
+}
+
+///How a team did in one year, see DataManager::get_team_history
+#[derive(Debug, Serialize, ToSchema, Deserialize)]
+pub struct TeamSeason {
+    year: u32,
+
+    //empty when statbotics failed or the team did not play that year
+    unitless_epa: Option<f64>,
+    norm_epa: Option<f64>,
+
+    sources: Vec<SourceStatus>,
+}
+
+///Checks the sources that are down every PROBE_INTERVAL and puts them back once they answer
+fn spawn_health_probe(
+    health: SourceHealth,
+    tba: TheBlueAllience,
+    statbotics: Statbotics,
+    frc: Option<FrcEvents>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PROBE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if health.is_down("tba") {
+                health.record("tba", tba.check().await);
+            }
+            if health.is_down("statbotics") {
+                health.record("statbotics", statbotics.check().await);
+            }
+            if let Some(frc) = &frc {
+                if health.is_down("frc") {
+                    health.record("frc", frc.check().await);
+                }
+            }
+        }
+    });
+}
+
+///What is being played at an event right now, see DataManager::get_current_match
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct CurrentMatch {
+    event: String,
+    ///none before the first match and after the last one
+    current: Option<MatchNumber>,
+    next: Option<MatchNumber>,
+    ///unix time the current match started, or is expected to
+    current_start: Option<u64>,
+    next_start: Option<u64>,
+}
+
+impl CurrentMatch {
+    ///The current match is the last one that has started, by its actual start time or failing
+    ///that the predicted or scheduled one. Once it has been going for longer than `dead_after`
+    ///the next match takes over even if TBA has not caught up yet.
+    pub fn new<S: Season>(
+        event: String,
+        mut matches: Vec<TbaMatchData<S>>,
+        now: u64,
+        dead_after: Duration,
+    ) -> Self {
+        let start = |m: &TbaMatchData<S>| m.actual_time.or(m.predicted_time).or(m.time);
+
+        //matches without any time go to the end, nothing can be said about when they are
+        matches.sort_by_key(|m| {
+            (
+                m.time.is_none(),
+                m.time,
+                m.match_number.level.clone(),
+                m.match_number.number,
+            )
+        });
+
+        let started = matches
+            .iter()
+            .rposition(|m| start(m).is_some_and(|time| time <= now));
+
+        let current = match started {
+            Some(index)
+                if start(&matches[index]).is_some_and(|time| now - time > dead_after.as_secs()) =>
+            {
+                index + 1
+            }
+            Some(index) => index,
+            //nothing has started, the first match is up next
+            None => {
+                let next = matches.first();
+                return Self {
+                    event,
+                    current: None,
+                    next: next.map(|m| m.match_number.clone()),
+                    current_start: None,
+                    next_start: next.and_then(start),
+                };
+            }
+        };
 
-    unitless_epa: f64,
-    norm_epa: f64,
+        let current_match = matches.get(current);
+        let next_match = matches.get(current + 1);
+        Self {
+            event,
+            current: current_match.map(|m| m.match_number.clone()),
+            next: next_match.map(|m| m.match_number.clone()),
+            current_start: current_match.and_then(start),
+            next_start: next_match.and_then(start),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
-pub struct MatchData {
+#[serde(bound = "")]
+pub struct MatchData<S: Season> {
+    //the schedule fields are empty when no schedule source answered and the predictions when
+    //statbotics did not, `sources` says which
     winner: Option<Allience>,
     predicted_winner: Option<Allience>,
-    red_win_prob: f64,
-    red_allience: [u32; 3],
-    blue_allience: [u32; 3],
-    red_score: u32,
-    blue_score: u32,
-    red_score_breakdown: TbaScoreBreakdown,
-    blue_score_breakdown: TbaScoreBreakdown,
-    predicted_red_score: f64,
-    predicted_blue_score: f64,
+    red_win_prob: Option<f64>,
+    red_allience: Option<[u32; 3]>,
+    blue_allience: Option<[u32; 3]>,
+    red_score: Option<u32>,
+    blue_score: Option<u32>,
+    ///none until the match has been played
+    red_score_breakdown: Option<S::ScoreBreakdown>,
+    blue_score_breakdown: Option<S::ScoreBreakdown>,
+    predicted_red_score: Option<f64>,
+    predicted_blue_score: Option<f64>,
     event: String,
     match_number: MatchNumber,
+    sources: Vec<SourceStatus>,
 }
 
-#[derive(Serialize, Deserialize, ToSchema, Debug)]
-pub struct TeamMatchReport {
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+#[serde(bound = "")]
+pub struct TeamMatchReport<S: Season> {
     //unchanging
     pub team_number: u32,
 
@@ -384,74 +1670,227 @@ pub struct TeamMatchReport {
 
     pub notes: String,
 
-    pub data: season::MatchData2024,
+    pub data: S::MatchData,
 
     //does not change but should still never be accessed
     pub team_spesific_data: Option<HashMap<String, serde_json::Value>>,
 
     //unix epoch
     pub timestamp: u64,
-}
 
-impl TeamMatchReport {
-    pub fn avg(data: Vec<Self>) -> Result<TeamMatchReport> {
-        //check to make sure data is from the same team and match
+    #[serde(default)]
+    pub visibility: Visibility,
+    ///keeps the notes and team_spesific_data to the recording team when the rest is shared
+    #[serde(default)]
+    pub private_notes: bool,
+}
 
-        let mut team_spesific_data: HashMap<String, Value> = HashMap::new();
-        data.iter().for_each(|x| {
-            if let Some(tsd) = x.team_spesific_data {
-                team_spesific_data.extend(tsd)
-            }
-        });
+///Who besides the recording team can see a report
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Default, PartialEq)]
+pub enum Visibility {
+    ///everyone, every report was public before there was a choice
+    #[default]
+    Public,
+    Private,
+    ///these teams, eg. the teams pooling their data at an event
+    Shared(Vec<u32>),
+}
 
-        Ok(Self {
-            team_number: match data.iter().all(|x| x.team_number == data[0].team_number) {
-                true => data[0].team_number,
-                false => 0,
-            },
+impl<S: Season> Shared for TeamMatchReport<S> {
+    fn recording_team(&self) -> u32 {
+        self.recording_team_number
+    }
 
-            recording_team_number: match data
-                .iter()
-                .all(|x| x.recording_team_number == data[0].recording_team_number)
-            {
-                true => data[0].recording_team_number,
-                false => 0,
-            },
+    fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
 
-            team_member: match data.iter().all(|x| x.team_member == data[0].team_member) {
-                true => data[0].team_member.clone(),
-                false => "".to_string(),
-            },
+    fn redact(mut self) -> Self {
+        if self.private_notes {
+            self.notes = String::new();
+            self.team_spesific_data = None;
+        }
+        self
+    }
+}
 
-            notes: "".to_string(),
+impl<S: Season> TeamMatchReport<S> {
+    ///Aggregates a set of reports about the same team at the same event.
+    ///This works for all the reports from one match as well as every match at the event.
+    pub fn avg(data: Vec<Self>) -> Result<TeamMatchReportAvg<S>> {
+        let first = data.first().ok_or(anyhow!(StatusCode::NO_CONTENT))?;
 
-            event: match data.iter().all(|x| x.event == data[0].event) {
-                true => data[0].event.clone(),
-                false => "".to_string(),
-            },
+        //check to make sure data is from the same team and event
+        if !data
+            .iter()
+            .all(|x| x.team_number == first.team_number && x.event == first.event)
+        {
+            return Err(anyhow!(
+                "can not average reports from different teams or events"
+            ));
+        }
 
-            match_number: todo!(),
+        let mut matches: Vec<MatchNumber> = Vec::new();
+        for report in data.iter() {
+            if !matches.contains(&report.match_number) {
+                matches.push(report.match_number.clone());
+            }
+        }
 
-            data: season::MatchData2024::avg(),
-            team_spesific_data: Some(team_spesific_data),
-            timestamp: todo!(),
+        Ok(TeamMatchReportAvg {
+            team_number: first.team_number,
+            event: first.event.clone(),
+            report_count: data.len() as u32,
+            matches,
+            data: S::avg(&data.iter().map(|x| x.data.clone()).collect::<Vec<_>>()),
+            first_timestamp: data.iter().map(|x| x.timestamp).min().unwrap_or(0),
+            last_timestamp: data.iter().map(|x| x.timestamp).max().unwrap_or(0),
         })
     }
 }
 
+///Aggregated team match reports, see TeamMatchReport::avg
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+#[serde(bound = "")]
+pub struct TeamMatchReportAvg<S: Season> {
+    pub team_number: u32,
+    pub event: String,
+
+    ///how many reports went into this
+    pub report_count: u32,
+    ///every match the reports covered
+    pub matches: Vec<MatchNumber>,
+
+    pub data: S::MatchDataAvg,
+
+    //unix epoch
+    pub first_timestamp: u64,
+    pub last_timestamp: u64,
+}
+
 //impl TeamMatchReport
 
-#[derive(Deserialize, Serialize, ToSchema)]
-pub struct TeamPitReport {
+///Several reports on one robot in one match merged into one, see DataManager::get_match_consensus
+#[derive(Serialize, ToSchema)]
+#[serde(bound = "")]
+pub struct MatchConsensus<S: Season> {
     team_number: u32,
-    recording_team: u32,
-    team_member: String,
     event: String,
+    match_number: MatchNumber,
 
-    data: season::PitData2024,
+    data: S::MatchData,
+    ///0 to 1, how much the scouts agreed and how many of them there were
+    confidence: f64,
+    ///every report that went in and how much it counted
+    scouts: Vec<ConsensusScout>,
+    ///values that were too far off to count
+    outliers: Vec<ConsensusOutlier>,
+    ///the notes from every report that had any
+    notes: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConsensusScout {
+    recording_team_number: u32,
+    team_member: String,
+    weight: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConsensusOutlier {
+    recording_team_number: u32,
+    team_member: String,
+    field: String,
+    value: f64,
+    consensus: f64,
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Clone)]
+#[serde(bound = "")]
+pub struct TeamPitReport<S: Season> {
+    pub team_number: u32,
+    pub recording_team: u32,
+    pub team_member: String,
+    pub event: String,
+
+    pub data: S::PitData,
+
+    #[serde(default)]
+    pub visibility: Visibility,
+}
+
+impl<S: Season> Shared for TeamPitReport<S> {
+    fn recording_team(&self) -> u32 {
+        self.recording_team
+    }
+
+    fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+}
+
+impl<S: Season> TeamPitReport<S> {
+    ///Merges the pit reports different scouts made about the same team at the same event
+    pub fn consensus(data: Vec<Self>) -> Result<PitConsensus<S>> {
+        let first = data.first().ok_or(anyhow!(StatusCode::NO_CONTENT))?;
+
+        if !data
+            .iter()
+            .all(|x| x.team_number == first.team_number && x.event == first.event)
+        {
+            return Err(anyhow!(
+                "can not merge pit reports from different teams or events"
+            ));
+        }
+
+        let mut recording_teams: Vec<u32> = Vec::new();
+        for report in data.iter() {
+            if !recording_teams.contains(&report.recording_team) {
+                recording_teams.push(report.recording_team);
+            }
+        }
+
+        let (merged, conflicts) =
+            S::pit_consensus(&data.iter().map(|x| x.data.clone()).collect::<Vec<_>>())
+                .ok_or(anyhow!(StatusCode::NO_CONTENT))?;
+
+        Ok(PitConsensus {
+            report: TeamPitReport {
+                team_number: first.team_number,
+                recording_team: match recording_teams.len() {
+                    1 => first.recording_team,
+                    _ => 0,
+                },
+                team_member: match data.iter().all(|x| x.team_member == first.team_member) {
+                    true => first.team_member.clone(),
+                    false => "".to_string(),
+                },
+                event: first.event.clone(),
+                data: merged,
+                //only ever sent back, never stored
+                visibility: Visibility::default(),
+            },
+            report_count: data.len() as u32,
+            recording_teams,
+            conflicts,
+        })
+    }
+}
+
+///The merged pit report for a team along with every field the scouts disagreed on
+#[derive(Serialize, ToSchema)]
+#[serde(bound = "")]
+pub struct PitConsensus<S: Season> {
+    ///recording_team and team_member are 0 and empty unless a single team or scout made every
+    ///report
+    pub report: TeamPitReport<S>,
+    pub report_count: u32,
+    pub recording_teams: Vec<u32>,
+    pub conflicts: Vec<FieldConflict>,
+}
+
+//declared in the order they are played, the derived Ord relies on it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
 pub enum Complevel {
     Practice,
     Qualifier,
@@ -459,12 +1898,24 @@ pub enum Complevel {
     Final,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 pub struct MatchNumber {
     pub number: u32,
     pub level: Complevel,
 }
 
+impl Complevel {
+    ///The variant name, this is how the level ends up in the databases
+    pub fn name(&self) -> &'static str {
+        match self {
+            Complevel::Practice => "Practice",
+            Complevel::Qualifier => "Qualifier",
+            Complevel::Semifinal => "Semifinal",
+            Complevel::Final => "Final",
+        }
+    }
+}
+
 impl MatchNumber {
     pub fn get_tba_string(&self) -> Result<String> {
         match self.level {
@@ -476,7 +1927,7 @@ impl MatchNumber {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum Allience {
     //TODO: may want to fix this patchwork solution
     #[serde(alias = "red")]
@@ -491,79 +1942,206 @@ pub struct Eventdata {
     name: String,
 }
 
-#[derive(Debug, Clone)]
-struct MatchScoutAssignments {
-    current_match: u32,
-    matches: HashMap<u32, Match>,
-}
+#[cfg(test)]
+mod tests {
+    use memory::MemoryStore;
+    use season::CurrentSeason;
+
+    use super::*;
+
+    ///Offline, every api call misses the empty event pack straight away
+    async fn manager() -> DataManager<CurrentSeason> {
+        let cache = HttpCache::from_pack(EventPack::default());
+        DataManager::with_clients(
+            Arc::new(MemoryStore::new(None, Duration::from_secs(60)).unwrap()),
+            TheBlueAllience::new(String::new(), cache.clone()),
+            Statbotics::new(cache),
+            None,
+            vec!["tba".to_string()],
+            vec![CurrentSeason::YEAR],
+            SourceHealth::new(),
+            true,
+            VerifiedKeys::new(),
+            SessionSigner::new(Some("secret".to_string()), Duration::from_secs(60)),
+            1,
+            Duration::from_secs(600),
+        )
+        .await
+        .unwrap()
+    }
 
-//TODO: finish this
-impl MatchScoutAssignments {
-    pub fn new() -> Self {
-        Self {
-            current_match: 0,
-            matches: HashMap::new(),
-        }
+    async fn issue(dm: &DataManager<CurrentSeason>, team: u32, auth: AuthLevel) -> String {
+        let request = KeyRequest {
+            name: "tablet".to_string(),
+            auth: Some(auth),
+            expires_in_days: None,
+        };
+        dm.issue_key(team, request, AuthLevel::ADMIN)
+            .await
+            .unwrap()
+            .info
+            .id
+    }
+
+    fn status<T>(result: Result<T>) -> Option<StatusCode> {
+        result
+            .err()
+            .and_then(|e| e.downcast_ref::<StatusCode>().copied())
     }
 
-    pub fn get_assignment(&mut self, match_num: u32) -> Slot {
-        if match_num > self.current_match {
-            self.current_match = match_num;
+    fn caller(team: u32, auth: AuthLevel) -> Caller {
+        Caller {
+            team,
+            key_id: String::new(),
+            name: String::new(),
+            auth,
         }
+    }
 
-        self.matches
-            .get_mut(&match_num)
-            .get_or_insert(&mut Match::new())
-            .get_assignment()
+    #[tokio::test]
+    async fn key_access_is_the_team_or_an_admin() {
+        let dm = manager().await;
+        assert_eq!(
+            dm.check_key_access(Some(&caller(254, AuthLevel::TEAM)), 254)
+                .unwrap(),
+            AuthLevel::TEAM
+        );
+        assert_eq!(
+            status(dm.check_key_access(Some(&caller(254, AuthLevel::TEAM)), 1)),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert!(dm
+            .check_key_access(Some(&caller(254, AuthLevel::ADMIN)), 1)
+            .is_ok());
+        assert_eq!(dm.check_key_access(None, 1).unwrap(), AuthLevel::ADMIN);
     }
-}
 
-#[derive(Debug, Clone)]
-struct Match {
-    slots: HashMap<Slot, bool>,
-}
+    #[tokio::test]
+    async fn only_an_admin_grants_admin() {
+        let dm = manager().await;
+        let request = |auth| KeyRequest {
+            name: "tablet".to_string(),
+            auth: Some(auth),
+            expires_in_days: None,
+        };
+        assert_eq!(
+            status(
+                dm.issue_key(254, request(AuthLevel::ADMIN), AuthLevel::TEAM)
+                    .await
+            ),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(
+                dm.issue_key(254, request(AuthLevel::SCOUT), AuthLevel::STRATEGIST)
+                    .await
+            ),
+            Some(StatusCode::FORBIDDEN)
+        );
 
-impl Match {
-    fn new() -> Self {
-        Self {
-            slots: Slot::iter()
-                .map(|s| {
-                    return (s, false);
-                })
-                .collect(),
-        }
+        let admin = issue(&dm, 254, AuthLevel::ADMIN).await;
+        let update = KeyUpdate {
+            name: Some("mine now".to_string()),
+            auth: None,
+            expires_in_days: None,
+        };
+        assert_eq!(
+            status(dm.update_key(254, admin, update, AuthLevel::TEAM).await),
+            Some(StatusCode::FORBIDDEN)
+        );
     }
 
-    fn get_assignment(&self) -> Slot {
-        //well this is mildly cursed
-        self.slots
-            .iter()
-            .find(|(slot, isfilled)| !isfilled.clone())
-            .unwrap_or((
-                &Slot::iter()
-                    .choose(&mut rand::thread_rng())
-                    .expect("This can not happen bc the iter is from the enum"),
-                &false,
-            ))
-            .0
-            .clone()
+    #[tokio::test]
+    async fn keys_of_other_teams_are_not_found() {
+        let dm = manager().await;
+        let id = issue(&dm, 254, AuthLevel::SCOUT).await;
+        assert_eq!(
+            status(dm.revoke_key(1, id.clone(), AuthLevel::ADMIN).await),
+            Some(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            status(
+                dm.delete_key(254, "nosuchkey".to_string(), AuthLevel::ADMIN)
+                    .await
+            ),
+            Some(StatusCode::NOT_FOUND)
+        );
+
+        dm.delete_key(254, id.clone(), AuthLevel::TEAM)
+            .await
+            .unwrap();
+        assert_eq!(
+            status(dm.delete_key(254, id, AuthLevel::TEAM).await),
+            Some(StatusCode::NOT_FOUND)
+        );
     }
-}
 
-#[derive(Debug, Clone, EnumIter, Hash, PartialEq, Eq)]
-enum Slot {
-    RED1,
-    RED2,
-    RED3,
-    BLUE1,
-    BLUE2,
-    BLUE3,
-}
+    #[tokio::test]
+    async fn revoked_keys_are_not_rotated() {
+        let dm = manager().await;
+        let id = issue(&dm, 254, AuthLevel::SCOUT).await;
+        dm.revoke_key(254, id.clone(), AuthLevel::TEAM)
+            .await
+            .unwrap();
+        assert_eq!(
+            status(dm.rotate_key(254, id, AuthLevel::TEAM).await),
+            Some(StatusCode::CONFLICT)
+        );
+        assert_eq!(
+            status(dm.change_own_key(None).await),
+            Some(StatusCode::BAD_REQUEST)
+        );
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
-struct ScoutingAssignment {
-    event: String,
-    match_number: MatchNumber,
-    slot: i32, //TODO: replace this with an enum
-    team_number: u32,
+    #[tokio::test]
+    async fn team_management() {
+        let dm = manager().await;
+        assert_eq!(
+            status(dm.set_team_disabled(254, true).await),
+            Some(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            status(dm.delete_team(254).await),
+            Some(StatusCode::NOT_FOUND)
+        );
+
+        issue(&dm, 254, AuthLevel::TEAM).await;
+        assert!(dm.set_team_disabled(254, true).await.unwrap().disabled);
+        let request = KeyRequest {
+            name: "tablet".to_string(),
+            auth: None,
+            expires_in_days: None,
+        };
+        assert_eq!(
+            status(dm.issue_key(254, request, AuthLevel::ADMIN).await),
+            Some(StatusCode::CONFLICT)
+        );
+
+        dm.delete_team(254).await.unwrap();
+        assert!(dm.get_teams().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn changed_default_key_still_works() {
+        let dm = manager().await;
+        let default = ApiKey::from_legacy(Auth {
+            _id: 254,
+            key: "oldkey".to_string(),
+            auth: AuthLevel::TEAM,
+        })
+        .unwrap();
+        let caller = default.caller();
+        dm.store.put_key(default).await.unwrap();
+
+        let changed = dm.change_own_key(Some(caller)).await.unwrap();
+        let login = LoginRequest {
+            team: 254,
+            key: changed.key,
+        };
+        assert!(dm.login(login).await.is_ok());
+        assert_eq!(
+            status(dm.verify_key(254, "oldkey").await),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
 }
@@ -1,15 +1,20 @@
 use anyhow::*;
+use async_trait::async_trait;
+use log::info;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use super::{
     super::{TeamMatchReport, TeamPitReport},
-    Complevel, MatchNumber,
+    keys::ApiKey,
+    season::Season,
+    store::{ScoutStore, Shared, Viewer},
+    MatchNumber,
 };
 use mongodb::{
     self,
-    bson::doc,
+    bson::{doc, Document},
     options::{Credential, FindOptions},
     Collection,
 };
@@ -21,9 +26,7 @@ use mongodb::{
 #[derive(Clone)]
 pub struct OpenScoutDB {
     db: mongodb::Client,
-    match_collection: Collection<TeamMatchReport>,
-    pit_collection: Collection<TeamPitReport>,
-    auth_collection: Collection<Auth>,
+    keys_collection: Collection<ApiKey>,
 }
 
 impl OpenScoutDB {
@@ -55,53 +58,108 @@ impl OpenScoutDB {
             .await?;
         println!("Pinged your deployment. You successfully connected to MongoDB!");
 
-        let match_collection: Collection<TeamMatchReport> =
-            client.database("main").collection("match");
-        let pit_collection: Collection<TeamPitReport> = client.database("main").collection("pit");
-        let auth_collection: Collection<Auth> = client.database("main").collection("auth");
+        let keys_collection: Collection<ApiKey> = client.database("main").collection("keys");
 
-        Ok(Self {
+        let db = Self {
             db: client,
-            match_collection,
-            pit_collection,
-            auth_collection,
-        })
+            keys_collection,
+        };
+        db.hash_legacy_keys().await?;
+        Ok(db)
+    }
+
+    ///Moves the plaintext keys from before keys were hashed over to the keys collection
+    async fn hash_legacy_keys(&self) -> Result<()> {
+        let legacy: Collection<Auth> = self.db.database("main").collection("auth");
+        let mut cursor = legacy.find(doc! {}).await?;
+        while cursor.advance().await? {
+            let auth: Auth = cursor.deserialize_current()?;
+            let team = auth._id;
+            //argon2 is slow on purpose, a big auth table would hold up the runtime
+            let key = tokio::task::spawn_blocking(move || ApiKey::from_legacy(auth)).await??;
+            self.write_key(key).await?;
+            legacy.delete_one(doc! {"_id": team}).await?;
+            info!("hashed the key for team {}", team);
+        }
+        Ok(())
     }
 
+    async fn write_key(&self, key: ApiKey) -> Result<()> {
+        self.keys_collection
+            .replace_one(doc! {"_id": &key._id}, &key)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    ///Each season gets its own collections, 2024 keeps the names from before there were seasons
+    fn collection<T: Send + Sync>(&self, kind: &str, season: u32) -> Collection<T> {
+        let name = match season {
+            2024 => kind.to_string(),
+            season => format!("{}_{}", kind, season),
+        };
+        self.db.database("main").collection(&name)
+    }
+
+    fn match_collection<S: Season>(&self) -> Collection<TeamMatchReport<S>> {
+        self.collection("match", S::YEAR)
+    }
+
+    fn pit_collection<S: Season>(&self) -> Collection<TeamPitReport<S>> {
+        self.collection("pit", S::YEAR)
+    }
+}
+
+///Only the reports the viewer can see, the results still go through Shared::for_viewer
+fn visible_to(viewer: Viewer, recording_field: &str) -> Document {
+    match viewer {
+        Viewer::All => doc! {},
+        Viewer::Team(team) => {
+            let mut own = Document::new();
+            own.insert(recording_field, team);
+            doc! {"$or": [
+                own,
+                //reports from before there was a visibility are public
+                {"visibility": {"$exists": false}},
+                {"visibility": "Public"},
+                {"visibility.Shared": team},
+            ]}
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Season> ScoutStore<S> for OpenScoutDB {
     //ngl this was easier than expected
-    pub async fn post_team_match_data(&self, data: TeamMatchReport) -> Result<()> {
-        self.match_collection.insert_one(data).await?;
+    async fn post_team_match_data(&self, data: TeamMatchReport<S>) -> Result<()> {
+        self.match_collection::<S>().insert_one(data).await?;
         Ok(())
     }
 
-    pub async fn post_team_pit_data(&self, data: TeamPitReport) -> Result<()> {
-        self.pit_collection.insert_one(data).await?;
+    async fn post_team_pit_data(&self, data: TeamPitReport<S>) -> Result<()> {
+        self.pit_collection::<S>().insert_one(data).await?;
         Ok(())
     }
 
-    pub async fn get_last_team_match_data(
+    async fn get_last_team_match_data(
         &self,
+        viewer: Viewer,
         team: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TeamMatchReport> {
-        let (name, num) = match match_number.level {
-            Complevel::Practice => ("Practice", match_number.number),
-            Complevel::Qualifier => ("Qualifier", match_number.number),
-            Complevel::Semifinal => ("Semifinal", match_number.number),
-            Complevel::Final => ("Final", match_number.number),
-        };
-
+    ) -> Result<TeamMatchReport<S>> {
         let data = self
-            .match_collection
+            .match_collection::<S>()
             .find_one(doc! {"$and": vec![
+            visible_to(viewer, "recording_team_number"),
             doc! {"team_number": team},
-            doc! {"match_number.number": num},
-            doc! {"match_number.level": name},
+            doc! {"match_number.number": match_number.number},
+            doc! {"match_number.level": match_number.level.name()},
             doc! {"event": event},
             ]})
             .sort(doc! {"timestamp": -1})
-            .await?;
+            .await?
+            .and_then(|r| r.for_viewer(viewer));
 
         match data {
             Some(data) => Ok(data),
@@ -109,36 +167,30 @@ impl OpenScoutDB {
         }
     }
 
-    pub async fn get_avg_team_match_data(
-        &self,
-        team_number: u32,
-        match_number: MatchNumber,
-        event: String,
-    ) -> Result<TeamMatchReport> {
-        todo!()
-    }
-
-    pub async fn get_all_team_match_data_by_team(
+    async fn get_all_team_match_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<Vec<TeamMatchReport>> {
+    ) -> Result<Vec<TeamMatchReport<S>>> {
         let mut cursor = self
-            .match_collection
+            .match_collection::<S>()
             .find(doc! {"$and": vec![
+            visible_to(viewer, "recording_team_number"),
             doc! {"team_number": team_number},
+            doc! {"recording_team_number": recording_team},
             doc! {"match_number.number": match_number.number},
-            doc! {"match_number.level": match_number.get_tba_string()?},
+            doc! {"match_number.level": match_number.level.name()},
             doc! {"event": event},
             ]})
             .await?;
 
-        let mut data: Vec<TeamMatchReport> = Vec::new();
+        let mut data: Vec<TeamMatchReport<S>> = Vec::new();
 
         while cursor.advance().await? {
-            data.push(cursor.deserialize_current()?);
+            data.extend(cursor.deserialize_current()?.for_viewer(viewer));
         }
 
         if data.len() == 0 {
@@ -149,25 +201,28 @@ impl OpenScoutDB {
     }
 
     ///
-    pub async fn get_team_match_data_by_induvidual(
+    async fn get_team_match_data_by_induvidual(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         recording_induvidual: String,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TeamMatchReport> {
+    ) -> Result<TeamMatchReport<S>> {
         let data = self
-            .match_collection
+            .match_collection::<S>()
             .find_one(doc! {"$and": vec![
+            visible_to(viewer, "recording_team_number"),
             doc! {"team_number": team_number},
             doc! {"recording_team_number": recording_team},
             doc! {"team_member": recording_induvidual},
             doc! {"match_number.number": match_number.number},
-            doc! {"match_number.level": match_number.get_tba_string()?},
+            doc! {"match_number.level": match_number.level.name()},
             doc! {"event": event},
             ]})
-            .await?;
+            .await?
+            .and_then(|r| r.for_viewer(viewer));
 
         match data {
             Some(data) => Ok(data),
@@ -175,47 +230,51 @@ impl OpenScoutDB {
         }
     }
 
-    pub async fn get_last_team_match_data_by_team(
+    async fn get_last_team_match_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TeamMatchReport> {
-        let data = self
-            .match_collection
+    ) -> Result<TeamMatchReport<S>> {
+        self.match_collection::<S>()
             .find_one(doc! {"$and": vec![
+            visible_to(viewer, "recording_team_number"),
             doc! {"team_number": team_number},
             doc! {"match_number.number": match_number.number},
-            doc! {"match_number.level": match_number.get_tba_string()?},
+            doc! {"match_number.level": match_number.level.name()},
             doc! {"event": event},
             doc! {"recording_team_number": recording_team}
             ]})
             .sort(doc! {"timestamp": -1})
             .await?
-            .ok_or(return Err(anyhow!(StatusCode::NO_CONTENT)));
+            .and_then(|r| r.for_viewer(viewer))
+            .ok_or(anyhow!(StatusCode::NO_CONTENT))
     }
 
-    pub async fn get_all_team_match_data(
+    async fn get_all_team_match_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<Vec<TeamMatchReport>> {
+    ) -> Result<Vec<TeamMatchReport<S>>> {
         let mut cursor = self
-            .match_collection
+            .match_collection::<S>()
             .find(doc! {"$and": vec![
+            visible_to(viewer, "recording_team_number"),
             doc! {"team_number": team_number},
             doc! {"match_number.number": match_number.number},
-            doc! {"match_number.level": match_number.get_tba_string()?},
+            doc! {"match_number.level": match_number.level.name()},
             doc! {"event": event},
             ]})
             .await?;
 
-        let mut data: Vec<TeamMatchReport> = Vec::new();
+        let mut data: Vec<TeamMatchReport<S>> = Vec::new();
 
         while cursor.advance().await? {
-            data.push(cursor.deserialize_current()?);
+            data.extend(cursor.deserialize_current()?.for_viewer(viewer));
         }
 
         if data.len() == 0 {
@@ -225,16 +284,51 @@ impl OpenScoutDB {
         Ok(data)
     }
 
+    async fn get_all_team_event_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        let mut cursor = self
+            .match_collection::<S>()
+            .find(doc! {"$and": vec![
+            visible_to(viewer, "recording_team_number"),
+            doc! {"team_number": team_number},
+            doc! {"event": event},
+            ]})
+            .await?;
+
+        let mut data: Vec<TeamMatchReport<S>> = Vec::new();
+
+        while cursor.advance().await? {
+            data.extend(cursor.deserialize_current()?.for_viewer(viewer));
+        }
+
+        if data.is_empty() {
+            return Err(anyhow!(StatusCode::NO_CONTENT));
+        }
+
+        Ok(data)
+    }
+
     //TODO: check if there is data here and return the appropriet status code if not
-    pub async fn get_last_team_pit_data(&self, team: u32, event: String) -> Result<TeamPitReport> {
+    async fn get_last_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
         let data = self
-            .pit_collection
+            .pit_collection::<S>()
             .find_one(doc! {"$and": vec![
+            visible_to(viewer, "recording_team"),
             doc! {"team_number": team},
             doc! {"event": event}
             ]})
             .sort(doc! {"timestamp": -1})
-            .await?;
+            .await?
+            .and_then(|r| r.for_viewer(viewer));
 
         match data {
             Some(data) => Ok(data),
@@ -242,34 +336,27 @@ impl OpenScoutDB {
         }
     }
 
-    ///
-    pub async fn get_avg_team_pit_data(
-        &self,
-        team_number: u32,
-        event: String,
-    ) -> Result<TeamPitReport> {
-        todo!()
-    }
-
-    pub async fn get_all_team_pit_data_by_team(
+    async fn get_all_team_pit_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         event: String,
-    ) -> Result<Vec<TeamPitReport>> {
+    ) -> Result<Vec<TeamPitReport<S>>> {
         let mut cursor = self
-            .pit_collection
+            .pit_collection::<S>()
             .find(doc! {"$and": vec![
+            visible_to(viewer, "recording_team"),
             doc! {"team_number": team_number},
             doc! {"event": event},
             doc! {"recording_team": recording_team},
             ]})
             .await?;
 
-        let mut data: Vec<TeamPitReport> = Vec::new();
+        let mut data: Vec<TeamPitReport<S>> = Vec::new();
 
         while cursor.advance().await? {
-            data.push(cursor.deserialize_current()?);
+            data.extend(cursor.deserialize_current()?.for_viewer(viewer));
         }
 
         if data.len() == 0 {
@@ -279,22 +366,25 @@ impl OpenScoutDB {
         Ok(data)
     }
 
-    pub async fn get_team_pit_data_by_induvidual(
+    async fn get_team_pit_data_by_induvidual(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         recording_induvidual: String,
         event: String,
-    ) -> Result<TeamPitReport> {
+    ) -> Result<TeamPitReport<S>> {
         let data = self
-            .pit_collection
+            .pit_collection::<S>()
             .find_one(doc! {"$and": vec![
+            visible_to(viewer, "recording_team"),
             doc! {"team_number": team_number},
             doc! {"event": event},
             doc! {"recording_team": recording_team},
             doc! {"team_member": recording_induvidual}
             ]})
-            .await?;
+            .await?
+            .and_then(|r| r.for_viewer(viewer));
 
         match data {
             Some(data) => Ok(data),
@@ -302,40 +392,45 @@ impl OpenScoutDB {
         }
     }
 
-    pub async fn get_last_team_pit_data_by_team(
+    async fn get_last_team_pit_data_by_team(
         &self,
+        viewer: Viewer,
         team_number: u32,
         recording_team: u32,
         event: String,
-    ) -> Result<TeamPitReport> {
-        self.pit_collection
+    ) -> Result<TeamPitReport<S>> {
+        self.pit_collection::<S>()
             .find_one(doc! {"$and": vec![
+                visible_to(viewer, "recording_team"),
                 doc! {"team_number": team_number},
                 doc! {"event": event},
                 doc! {"recording_team": recording_team},
             ]})
             .sort(doc! {"timestamp": -1})
             .await?
-            .ok_or(return Err(anyhow!(StatusCode::NO_CONTENT)));
+            .and_then(|r| r.for_viewer(viewer))
+            .ok_or(anyhow!(StatusCode::NO_CONTENT))
     }
 
-    pub async fn get_all_team_pit_data(
+    async fn get_all_team_pit_data(
         &self,
+        viewer: Viewer,
         team_number: u32,
         event: String,
-    ) -> Result<Vec<TeamPitReport>> {
+    ) -> Result<Vec<TeamPitReport<S>>> {
         let mut cursor = self
-            .pit_collection
+            .pit_collection::<S>()
             .find(doc! {"$and": vec![
+            visible_to(viewer, "recording_team"),
             doc! {"team_number": team_number},
             doc! {"event": event},
             ]})
             .await?;
 
-        let mut data: Vec<TeamPitReport> = Vec::new();
+        let mut data: Vec<TeamPitReport<S>> = Vec::new();
 
         while cursor.advance().await? {
-            data.push(cursor.deserialize_current()?);
+            data.extend(cursor.deserialize_current()?.for_viewer(viewer));
         }
 
         if data.len() == 0 {
@@ -345,41 +440,98 @@ impl OpenScoutDB {
         Ok(data)
     }
 
-    pub async fn check_auth(&self, team: u32) -> Result<Auth> {
-        let data = self.auth_collection.find(doc! {"_id": team}).await?;
+    async fn get_key(&self, id: &str) -> Result<ApiKey> {
+        self.keys_collection
+            .find_one(doc! {"_id": id})
+            .await?
+            .ok_or(anyhow!(StatusCode::UNAUTHORIZED))
+    }
+
+    async fn get_team_keys(&self, team: u32) -> Result<Vec<ApiKey>> {
+        let mut cursor = self.keys_collection.find(doc! {"team": team}).await?;
+        let mut keys = Vec::new();
+        while cursor.advance().await? {
+            keys.push(cursor.deserialize_current()?);
+        }
+        Ok(keys)
+    }
+
+    async fn get_all_keys(&self) -> Result<Vec<ApiKey>> {
+        let mut cursor = self.keys_collection.find(doc! {}).await?;
+        let mut keys = Vec::new();
+        while cursor.advance().await? {
+            keys.push(cursor.deserialize_current()?);
+        }
+        Ok(keys)
+    }
 
-        Ok(data.deserialize_current()?)
+    async fn put_key(&self, key: ApiKey) -> Result<()> {
+        self.write_key(key).await
     }
 
-    pub async fn add_auth(&self, auth: Auth) -> Result<()> {
-        self.auth_collection.insert_one(auth).await?;
+    async fn delete_key(&self, id: &str) -> Result<()> {
+        let deleted = self.keys_collection.delete_one(doc! {"_id": id}).await?;
+        if deleted.deleted_count == 0 {
+            return Err(anyhow!(StatusCode::NOT_FOUND));
+        }
         Ok(())
     }
 }
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Auth {
     pub _id: u32,
     pub key: String,
     pub auth: AuthLevel,
 }
-#[derive(PartialEq, Eq, PartialOrd, Debug, Serialize, Deserialize, ToSchema)]
+///What a key is for. The names stay as they are in the database, TEAM was the only kind of team
+///key before there were roles.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum AuthLevel {
+    ///runs the server, can do anything for any team
     ADMIN,
+    ///the team lead, everything for their own team
     TEAM,
+    ///reads the data, can not change anything
+    STRATEGIST,
+    ///posts reports and sees their own assignments and shifts
+    SCOUT,
+}
+
+///What a route needs, see session::Authorized
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Permission {
+    ///reports, stats and everything from TBA and statbotics
+    ReadData,
+    PostReports,
+    ///their own assignments and shifts
+    ScoutMatches,
+    ///setting the team's shift schedule
+    ManageScouts,
+    ///the team's own keys
+    ManageKeys,
+    ///adding teams and managing every team's keys
+    ManageServer,
+    ///a new secret for the key the caller is using
+    ChangeOwnKey,
 }
 
 impl AuthLevel {
-    fn index(&self) -> u8 {
+    pub fn allows(&self, permission: Permission) -> bool {
+        use Permission::*;
         match self {
-            AuthLevel::ADMIN => 0,
-            AuthLevel::TEAM => 1,
+            AuthLevel::ADMIN => true,
+            _ if permission == ChangeOwnKey => true,
+            AuthLevel::TEAM => permission != ManageServer,
+            AuthLevel::STRATEGIST => permission == ReadData,
+            AuthLevel::SCOUT => matches!(permission, PostReports | ScoutMatches),
         }
     }
-}
 
-impl Ord for AuthLevel {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.index().cmp(&other.index())
+    ///Whether a key with this role can hand out keys with `role`, only an admin can make admins
+    pub fn can_grant(&self, role: &AuthLevel) -> bool {
+        self.allows(Permission::ManageServer)
+            || (self.allows(Permission::ManageKeys) && *role != AuthLevel::ADMIN)
     }
 }
 
@@ -388,3 +540,53 @@ pub struct MongoAuth {
     username: String,
     password: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Permission; 7] = [
+        Permission::ReadData,
+        Permission::PostReports,
+        Permission::ScoutMatches,
+        Permission::ManageScouts,
+        Permission::ManageKeys,
+        Permission::ManageServer,
+        Permission::ChangeOwnKey,
+    ];
+
+    fn allowed(auth: AuthLevel) -> Vec<Permission> {
+        ALL.into_iter().filter(|p| auth.allows(*p)).collect()
+    }
+
+    #[test]
+    fn permissions_per_role() {
+        use Permission::*;
+        assert_eq!(allowed(AuthLevel::ADMIN), ALL);
+        assert_eq!(
+            allowed(AuthLevel::TEAM),
+            vec![
+                ReadData,
+                PostReports,
+                ScoutMatches,
+                ManageScouts,
+                ManageKeys,
+                ChangeOwnKey
+            ]
+        );
+        assert_eq!(allowed(AuthLevel::STRATEGIST), vec![ReadData, ChangeOwnKey]);
+        assert_eq!(
+            allowed(AuthLevel::SCOUT),
+            vec![PostReports, ScoutMatches, ChangeOwnKey]
+        );
+    }
+
+    #[test]
+    fn only_admins_grant_admin() {
+        assert!(AuthLevel::ADMIN.can_grant(&AuthLevel::ADMIN));
+        assert!(!AuthLevel::TEAM.can_grant(&AuthLevel::ADMIN));
+        assert!(AuthLevel::TEAM.can_grant(&AuthLevel::SCOUT));
+        assert!(!AuthLevel::STRATEGIST.can_grant(&AuthLevel::SCOUT));
+        assert!(!AuthLevel::SCOUT.can_grant(&AuthLevel::SCOUT));
+    }
+}
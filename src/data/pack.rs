@@ -0,0 +1,80 @@
+//! Offline event packs for venues without internet.
+//! A pack is every TBA and Statbotics response the server needs for a few events saved into one
+//! file by url. Running from a pack swaps the network for the file inside the response cache so
+//! the clients themselves do not know the difference.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::*;
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cache::HttpCache, season::Season, statbotics::Statbotics, theblueallience::TheBlueAllience,
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EventPack {
+    ///unix time the pack was downloaded
+    pub created: i64,
+    pub events: Vec<String>,
+    ///raw response bodies by url
+    pub responses: HashMap<String, String>,
+}
+
+impl EventPack {
+    pub fn load(path: &Path) -> Result<Self> {
+        let pack: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        info!(
+            "loaded the event pack for {} from {}",
+            pack.events.join(", "),
+            path.display()
+        );
+        Ok(pack)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    ///Downloads the event list, and for every event the team list, the match schedule, rankings,
+    ///OPRs and the Statbotics EPA of every team. The events are expected to be from season `S`.
+    pub async fn download<S: Season>(tba_key: String, events: Vec<String>) -> Result<Self> {
+        let cache = HttpCache::new();
+        let tba = TheBlueAllience::new(tba_key, cache.clone());
+        tba.check().await?;
+        let statbotics = Statbotics::new(cache.clone());
+        statbotics.check().await?;
+
+        tba.get_event_keys(S::YEAR).await?;
+        tba.get_event_list(S::YEAR).await?;
+
+        for event in events.iter() {
+            info!("downloading {}", event);
+            let teams = tba.get_team_list(event.clone()).await?;
+            tba.get_match_data_list::<S>(event.clone()).await?;
+
+            //neither of these exist until the first matches are played
+            if let Err(e) = tba.get_oprs(event.clone()).await {
+                warn!("no oprs for {} yet: {}", event, e);
+            }
+            if let Err(e) = tba.get_rankings(event.clone()).await {
+                warn!("no rankings for {} yet: {}", event, e);
+            }
+
+            for team in teams {
+                if let Err(e) = statbotics.get_team_data(team, S::YEAR).await {
+                    warn!("no statbotics data for {}: {}", team, e);
+                }
+            }
+        }
+
+        Ok(Self {
+            created: Utc::now().timestamp(),
+            events,
+            responses: cache.responses()?,
+        })
+    }
+}
@@ -0,0 +1,264 @@
+//! 2024, Crescendo.
+//! This is a collection of tracked values that every scouting app really should have.
+//! Values contained in the sturcts of these files should never be accessed by anything other than
+//! serde.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    super::stats::{majority, Consensus, EnumStats, FieldConflict, NumericStats},
+    AutoLine, Drivebase, FieldCheck, ScoreBreakdown, Season,
+};
+
+///The 2024 game
+#[derive(ToSchema, Debug, Clone)]
+pub struct Crescendo;
+
+impl Season for Crescendo {
+    const YEAR: u32 = 2024;
+
+    type MatchData = MatchData2024;
+    type MatchDataAvg = MatchData2024Avg;
+    type PitData = PitData2024;
+    type ScoreBreakdown = ScoreBreakdown2024;
+
+    fn avg(data: &[MatchData2024]) -> MatchData2024Avg {
+        MatchData2024Avg {
+            notes_speaker_auto: NumericStats::of(data, |d| d.notes_speaker_auto as f64),
+            notes_speaker_teleop: NumericStats::of(data, |d| d.notes_speaker_teleop as f64),
+            notes_amp_teleop: NumericStats::of(data, |d| d.notes_amp_teleop as f64),
+            endgame: EnumStats::of(data, |d| &d.endgame),
+        }
+    }
+
+    fn numeric_fields(data: &MatchData2024) -> Vec<(&'static str, f64)> {
+        vec![
+            ("notes_speaker_auto", data.notes_speaker_auto as f64),
+            ("notes_speaker_teleop", data.notes_speaker_teleop as f64),
+            ("notes_amp_teleop", data.notes_amp_teleop as f64),
+        ]
+    }
+
+    fn pit_consensus(data: &[PitData2024]) -> Option<(PitData2024, Vec<FieldConflict>)> {
+        let mut conflicts = Vec::new();
+
+        let consensus = PitData2024 {
+            speaker: majority(data, "speaker", |d| &d.speaker, &mut conflicts)?,
+            amp: majority(data, "amp", |d| &d.amp, &mut conflicts)?,
+            posible_endgame: majority(
+                data,
+                "posible_endgame",
+                |d| &d.posible_endgame,
+                &mut conflicts,
+            )?,
+            drivebase: majority(data, "drivebase", |d| &d.drivebase, &mut conflicts)?,
+            can_move_auto: majority(data, "can_move_auto", |d| &d.can_move_auto, &mut conflicts)?,
+            expected_notes_auto: majority(
+                data,
+                "expected_notes_auto",
+                |d| &d.expected_notes_auto,
+                &mut conflicts,
+            )?,
+        };
+
+        Some((consensus, conflicts))
+    }
+
+    fn match_consensus(c: &mut Consensus<MatchData2024>) -> MatchData2024 {
+        MatchData2024 {
+            notes_speaker_auto: c.count("notes_speaker_auto", |d| d.notes_speaker_auto),
+            notes_speaker_teleop: c.count("notes_speaker_teleop", |d| d.notes_speaker_teleop),
+            notes_amp_teleop: c.count("notes_amp_teleop", |d| d.notes_amp_teleop),
+            endgame: c.vote(|d| &d.endgame),
+        }
+    }
+
+    fn check_robot(
+        data: &MatchData2024,
+        breakdown: &ScoreBreakdown2024,
+        station: usize,
+    ) -> Vec<FieldCheck> {
+        let mut checks = Vec::new();
+
+        //the trap is scored per stage and not per robot so a trap only counts as a climb here
+        if let Some(official) = breakdown.robot_endgame(station) {
+            let agrees = matches!(
+                (&data.endgame, official),
+                (Endgame::None, StageResult2024::None)
+                    | (Endgame::Park, StageResult2024::Parked)
+                    | (
+                        Endgame::Climb | Endgame::ClimbAndTrap,
+                        StageResult2024::CenterStage
+                            | StageResult2024::StageLeft
+                            | StageResult2024::StageRight
+                    )
+            );
+            checks.push(FieldCheck::new("endgame", &data.endgame, official, agrees));
+        }
+
+        checks
+    }
+
+    fn alliance_fields(data: &MatchData2024) -> Vec<(&'static str, f64)> {
+        vec![
+            ("notes_speaker_auto", data.notes_speaker_auto as f64),
+            ("notes_speaker_teleop", data.notes_speaker_teleop as f64),
+            ("notes_amp_teleop", data.notes_amp_teleop as f64),
+        ]
+    }
+
+    fn official_totals(breakdown: &ScoreBreakdown2024) -> Vec<(&'static str, f64)> {
+        vec![
+            ("notes_speaker_auto", breakdown.autoSpeakerNoteCount as f64),
+            //the scouts do not tell amplified notes apart
+            (
+                "notes_speaker_teleop",
+                (breakdown.teleopSpeakerNoteCount + breakdown.teleopSpeakerNoteAmplifiedCount)
+                    as f64,
+            ),
+            ("notes_amp_teleop", breakdown.teleopAmpNoteCount as f64),
+        ]
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct MatchData2024 {
+    pub notes_speaker_auto: u32,
+    pub notes_speaker_teleop: u32,
+    pub notes_amp_teleop: u32,
+    pub endgame: Endgame,
+}
+
+///The aggregate of a set of MatchData2024, one entry per field
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct MatchData2024Avg {
+    pub notes_speaker_auto: NumericStats,
+    pub notes_speaker_teleop: NumericStats,
+    pub notes_amp_teleop: NumericStats,
+    pub endgame: EnumStats<Endgame>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct PitData2024 {
+    speaker: bool,
+    amp: bool,
+    posible_endgame: Endgame,
+
+    drivebase: Drivebase,
+
+    can_move_auto: bool,
+    expected_notes_auto: bool,
+}
+
+// yearly support enums, do not use outside of team match report.
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub enum Endgame {
+    ClimbAndTrap,
+    Climb,
+    Park,
+    None,
+}
+
+///Where TBA says a robot ended the match
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub enum StageResult2024 {
+    None,
+    Parked,
+    CenterStage,
+    StageLeft,
+    StageRight,
+}
+
+///One alliance's `score_breakdown` from TBA for a 2024 match. Anything TBA left out is 0 (or
+///None for the per robot fields).
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ScoreBreakdown2024 {
+    totalPoints: u32,
+    autoPoints: u32,
+    teleopPoints: u32,
+    adjustPoints: u32,
+    foulPoints: u32,
+    foulCount: u32,
+    techFoulCount: u32,
+    rp: u32,
+
+    //per robot, in driver station order
+    autoLineRobot1: Option<AutoLine>,
+    autoLineRobot2: Option<AutoLine>,
+    autoLineRobot3: Option<AutoLine>,
+    endGameRobot1: Option<StageResult2024>,
+    endGameRobot2: Option<StageResult2024>,
+    endGameRobot3: Option<StageResult2024>,
+
+    autoLeavePoints: u32,
+    autoAmpNoteCount: u32,
+    autoAmpNotePoints: u32,
+    autoSpeakerNoteCount: u32,
+    autoSpeakerNotePoints: u32,
+    autoTotalNotePoints: u32,
+
+    teleopAmpNoteCount: u32,
+    teleopAmpNotePoints: u32,
+    teleopSpeakerNoteCount: u32,
+    teleopSpeakerNotePoints: u32,
+    teleopSpeakerNoteAmplifiedCount: u32,
+    teleopSpeakerNoteAmplifiedPoints: u32,
+    teleopTotalNotePoints: u32,
+
+    endGameParkPoints: u32,
+    endGameOnStagePoints: u32,
+    endGameHarmonyPoints: u32,
+    endGameNoteInTrapPoints: u32,
+    endGameSpotLightBonusPoints: u32,
+    endGameTotalStagePoints: u32,
+    micCenterStage: bool,
+    micStageLeft: bool,
+    micStageRight: bool,
+    trapCenterStage: bool,
+    trapStageLeft: bool,
+    trapStageRight: bool,
+
+    coopNotePlayed: bool,
+    coopertitionBonusAchieved: bool,
+    coopertitionCriteriaMet: bool,
+    melodyBonusAchieved: bool,
+    melodyBonusThreshold: u32,
+    melodyBonusThresholdCoop: u32,
+    melodyBonusThresholdNonCoop: u32,
+    ensembleBonusAchieved: bool,
+    ensembleBonusOnStageRobotsThreshold: u32,
+    ensembleBonusStagePointsThreshold: u32,
+
+    g206Penalty: bool,
+    g408Penalty: bool,
+    g424Penalty: bool,
+}
+
+impl ScoreBreakdown2024 {
+    //the 2024 reports do not track leaving so only the endgame is looked up
+    pub fn robot_endgame(&self, station: usize) -> Option<&StageResult2024> {
+        [
+            &self.endGameRobot1,
+            &self.endGameRobot2,
+            &self.endGameRobot3,
+        ]
+        .get(station)?
+        .as_ref()
+    }
+}
+
+impl ScoreBreakdown for ScoreBreakdown2024 {
+    fn from_totals(total: Option<u32>, auto: Option<u32>, foul: Option<u32>) -> Self {
+        let (total, auto, foul) = (total.unwrap_or(0), auto.unwrap_or(0), foul.unwrap_or(0));
+        Self {
+            totalPoints: total,
+            autoPoints: auto,
+            teleopPoints: total.saturating_sub(auto + foul),
+            foulPoints: foul,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Everything that changes with the game.
+//! A season is a marker type implementing [`Season`] that bundles the match and pit report
+//! structs, how they get aggregated and the TBA score breakdown for that year. The rest of the
+//! server is generic over it so a new game only needs a new module here and [`CurrentSeason`]
+//! pointed at it.
+
+pub mod crescendo;
+pub mod reefscape;
+
+use std::fmt::Debug;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::stats::{variant_name, Consensus, FieldConflict};
+
+///The game this server scouts
+pub type CurrentSeason = reefscape::Reefscape;
+
+//the marker types need ToSchema so the openapi schema names can include the season
+pub trait Season: ToSchema + Debug + Clone + Send + Sync + 'static {
+    ///the year the game is played, used in the TBA and Statbotics urls and to tag stored reports
+    const YEAR: u32;
+
+    ///what a scout records about one robot in one match
+    type MatchData: SeasonData;
+    ///the aggregate of several MatchData, one entry per field
+    type MatchDataAvg: SeasonData;
+    ///what a scout records in the pits
+    type PitData: SeasonData;
+    ///the per alliance `score_breakdown` TBA gives for a played match
+    type ScoreBreakdown: ScoreBreakdown;
+
+    fn avg(data: &[Self::MatchData]) -> Self::MatchDataAvg;
+
+    ///Every numeric field by name, used for things like trends that work on any field
+    fn numeric_fields(data: &Self::MatchData) -> Vec<(&'static str, f64)>;
+
+    ///Merges pit reports from several scouts into one, fields they did not all agree on are
+    ///returned as conflicts. None when there is nothing to merge.
+    fn pit_consensus(data: &[Self::PitData]) -> Option<(Self::PitData, Vec<FieldConflict>)>;
+
+    ///Merges what several scouts saw one robot do in one match, field by field
+    fn match_consensus(consensus: &mut Consensus<Self::MatchData>) -> Self::MatchData;
+
+    ///Compares what a scout saw one robot do with the official per robot results. `station` is
+    ///where the robot was in its alliance (0 to 2), anything the breakdown does not have is left
+    ///out.
+    fn check_robot(
+        data: &Self::MatchData,
+        breakdown: &Self::ScoreBreakdown,
+        station: usize,
+    ) -> Vec<FieldCheck>;
+
+    ///The fields of a report that add up over an alliance to something in the score breakdown,
+    ///by the same names official_totals uses
+    fn alliance_fields(data: &Self::MatchData) -> Vec<(&'static str, f64)>;
+
+    ///The official alliance totals for the alliance_fields, anything the breakdown does not have
+    ///is left out
+    fn official_totals(breakdown: &Self::ScoreBreakdown) -> Vec<(&'static str, f64)>;
+}
+
+///Everything a season struct needs to go through the api and into the databases
+pub trait SeasonData:
+    Serialize + DeserializeOwned + ToSchema + Debug + Clone + Send + Sync + 'static
+{
+}
+
+impl<T> SeasonData for T where
+    T: Serialize + DeserializeOwned + ToSchema + Debug + Clone + Send + Sync + 'static
+{
+}
+
+pub trait ScoreBreakdown: SeasonData {
+    ///For sources that only give the totals, teleop is whatever is left over and everything else
+    ///is left empty
+    fn from_totals(total: Option<u32>, auto: Option<u32>, foul: Option<u32>) -> Self;
+}
+
+///One field of a scouted report next to what the official results say
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldCheck {
+    pub field: String,
+    pub scouted: String,
+    pub official: String,
+    ///the official values are not always the same words as the scouted ones, this is whether
+    ///they mean the same thing
+    pub agrees: bool,
+}
+
+impl FieldCheck {
+    pub fn new<A: Serialize, B: Serialize>(
+        field: &str,
+        scouted: &A,
+        official: &B,
+        agrees: bool,
+    ) -> Self {
+        Self {
+            field: field.to_string(),
+            scouted: variant_name(scouted),
+            official: variant_name(official),
+            agrees,
+        }
+    }
+}
+
+///How TBA says whether a robot left its starting line in auto
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+pub enum AutoLine {
+    Yes,
+    No,
+}
+
+///What a robot drives on, the same every year so every season's pit data uses this one
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub enum Drivebase {
+    Differential,
+    Mecanum,
+    Swerve,
+    Other(String),
+}
@@ -0,0 +1,495 @@
+//! 2025, Reefscape.
+//! Coral goes on the four levels of the reef, algae goes in the processor or the net and the
+//! robots hang off the barge at the end.
+//! Values contained in the sturcts of these files should never be accessed by anything other than
+//! serde.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    super::stats::{majority, Consensus, EnumStats, FieldConflict, NumericStats},
+    AutoLine, Drivebase, FieldCheck, ScoreBreakdown, Season,
+};
+
+///The 2025 game
+#[derive(ToSchema, Debug, Clone)]
+pub struct Reefscape;
+
+impl Season for Reefscape {
+    const YEAR: u32 = 2025;
+
+    type MatchData = MatchData2025;
+    type MatchDataAvg = MatchData2025Avg;
+    type PitData = PitData2025;
+    type ScoreBreakdown = ScoreBreakdown2025;
+
+    fn avg(data: &[MatchData2025]) -> MatchData2025Avg {
+        MatchData2025Avg {
+            //the mean of this is how often they leave
+            leave: NumericStats::of(data, |d| d.leave as u32 as f64),
+            coral_l1_auto: NumericStats::of(data, |d| d.coral_l1_auto as f64),
+            coral_l2_auto: NumericStats::of(data, |d| d.coral_l2_auto as f64),
+            coral_l3_auto: NumericStats::of(data, |d| d.coral_l3_auto as f64),
+            coral_l4_auto: NumericStats::of(data, |d| d.coral_l4_auto as f64),
+            algae_processor_auto: NumericStats::of(data, |d| d.algae_processor_auto as f64),
+            algae_net_auto: NumericStats::of(data, |d| d.algae_net_auto as f64),
+            coral_l1_teleop: NumericStats::of(data, |d| d.coral_l1_teleop as f64),
+            coral_l2_teleop: NumericStats::of(data, |d| d.coral_l2_teleop as f64),
+            coral_l3_teleop: NumericStats::of(data, |d| d.coral_l3_teleop as f64),
+            coral_l4_teleop: NumericStats::of(data, |d| d.coral_l4_teleop as f64),
+            algae_processor_teleop: NumericStats::of(data, |d| d.algae_processor_teleop as f64),
+            algae_net_teleop: NumericStats::of(data, |d| d.algae_net_teleop as f64),
+            endgame: EnumStats::of(data, |d| &d.endgame),
+            points: NumericStats::of(data, |d| d.points() as f64),
+        }
+    }
+
+    fn numeric_fields(data: &MatchData2025) -> Vec<(&'static str, f64)> {
+        vec![
+            ("coral_l1_auto", data.coral_l1_auto as f64),
+            ("coral_l2_auto", data.coral_l2_auto as f64),
+            ("coral_l3_auto", data.coral_l3_auto as f64),
+            ("coral_l4_auto", data.coral_l4_auto as f64),
+            ("algae_processor_auto", data.algae_processor_auto as f64),
+            ("algae_net_auto", data.algae_net_auto as f64),
+            ("coral_l1_teleop", data.coral_l1_teleop as f64),
+            ("coral_l2_teleop", data.coral_l2_teleop as f64),
+            ("coral_l3_teleop", data.coral_l3_teleop as f64),
+            ("coral_l4_teleop", data.coral_l4_teleop as f64),
+            ("algae_processor_teleop", data.algae_processor_teleop as f64),
+            ("algae_net_teleop", data.algae_net_teleop as f64),
+            ("points", data.points() as f64),
+        ]
+    }
+
+    fn pit_consensus(data: &[PitData2025]) -> Option<(PitData2025, Vec<FieldConflict>)> {
+        let mut conflicts = Vec::new();
+
+        let consensus = PitData2025 {
+            drivebase: majority(data, "drivebase", |d| &d.drivebase, &mut conflicts)?,
+            coral_ground_intake: majority(
+                data,
+                "coral_ground_intake",
+                |d| &d.coral_ground_intake,
+                &mut conflicts,
+            )?,
+            coral_station_intake: majority(
+                data,
+                "coral_station_intake",
+                |d| &d.coral_station_intake,
+                &mut conflicts,
+            )?,
+            coral_l1: majority(data, "coral_l1", |d| &d.coral_l1, &mut conflicts)?,
+            coral_l2: majority(data, "coral_l2", |d| &d.coral_l2, &mut conflicts)?,
+            coral_l3: majority(data, "coral_l3", |d| &d.coral_l3, &mut conflicts)?,
+            coral_l4: majority(data, "coral_l4", |d| &d.coral_l4, &mut conflicts)?,
+            algae_ground_intake: majority(
+                data,
+                "algae_ground_intake",
+                |d| &d.algae_ground_intake,
+                &mut conflicts,
+            )?,
+            algae_reef_removal: majority(
+                data,
+                "algae_reef_removal",
+                |d| &d.algae_reef_removal,
+                &mut conflicts,
+            )?,
+            processor: majority(data, "processor", |d| &d.processor, &mut conflicts)?,
+            net: majority(data, "net", |d| &d.net, &mut conflicts)?,
+            posible_endgame: majority(
+                data,
+                "posible_endgame",
+                |d| &d.posible_endgame,
+                &mut conflicts,
+            )?,
+            can_leave_auto: majority(
+                data,
+                "can_leave_auto",
+                |d| &d.can_leave_auto,
+                &mut conflicts,
+            )?,
+            expected_coral_auto: majority(
+                data,
+                "expected_coral_auto",
+                |d| &d.expected_coral_auto,
+                &mut conflicts,
+            )?,
+        };
+
+        Some((consensus, conflicts))
+    }
+
+    fn match_consensus(c: &mut Consensus<MatchData2025>) -> MatchData2025 {
+        MatchData2025 {
+            leave: c.vote(|d| &d.leave),
+            coral_l1_auto: c.count("coral_l1_auto", |d| d.coral_l1_auto),
+            coral_l2_auto: c.count("coral_l2_auto", |d| d.coral_l2_auto),
+            coral_l3_auto: c.count("coral_l3_auto", |d| d.coral_l3_auto),
+            coral_l4_auto: c.count("coral_l4_auto", |d| d.coral_l4_auto),
+            algae_processor_auto: c.count("algae_processor_auto", |d| d.algae_processor_auto),
+            algae_net_auto: c.count("algae_net_auto", |d| d.algae_net_auto),
+            coral_l1_teleop: c.count("coral_l1_teleop", |d| d.coral_l1_teleop),
+            coral_l2_teleop: c.count("coral_l2_teleop", |d| d.coral_l2_teleop),
+            coral_l3_teleop: c.count("coral_l3_teleop", |d| d.coral_l3_teleop),
+            coral_l4_teleop: c.count("coral_l4_teleop", |d| d.coral_l4_teleop),
+            algae_processor_teleop: c.count("algae_processor_teleop", |d| d.algae_processor_teleop),
+            algae_net_teleop: c.count("algae_net_teleop", |d| d.algae_net_teleop),
+            endgame: c.vote(|d| &d.endgame),
+        }
+    }
+
+    fn check_robot(
+        data: &MatchData2025,
+        breakdown: &ScoreBreakdown2025,
+        station: usize,
+    ) -> Vec<FieldCheck> {
+        let mut checks = Vec::new();
+
+        if let Some(official) = breakdown.robot_leave(station) {
+            let agrees = data.leave == (*official == AutoLine::Yes);
+            checks.push(FieldCheck::new("leave", &data.leave, official, agrees));
+        }
+
+        if let Some(official) = breakdown.robot_endgame(station) {
+            let agrees = matches!(
+                (&data.endgame, official),
+                (BargeEndgame::None, BargeResult2025::None)
+                    | (BargeEndgame::Park, BargeResult2025::Parked)
+                    | (BargeEndgame::ShallowCage, BargeResult2025::ShallowCage)
+                    | (BargeEndgame::DeepCage, BargeResult2025::DeepCage)
+            );
+            checks.push(FieldCheck::new("endgame", &data.endgame, official, agrees));
+        }
+
+        checks
+    }
+
+    fn alliance_fields(data: &MatchData2025) -> Vec<(&'static str, f64)> {
+        vec![
+            ("coral_l1_auto", data.coral_l1_auto as f64),
+            ("coral_l2_auto", data.coral_l2_auto as f64),
+            ("coral_l3_auto", data.coral_l3_auto as f64),
+            ("coral_l4_auto", data.coral_l4_auto as f64),
+            ("coral_l1_teleop", data.coral_l1_teleop as f64),
+            ("coral_l2_teleop", data.coral_l2_teleop as f64),
+            ("coral_l3_teleop", data.coral_l3_teleop as f64),
+            ("coral_l4_teleop", data.coral_l4_teleop as f64),
+            //TBA only counts algae for the whole match
+            (
+                "algae_processor",
+                (data.algae_processor_auto + data.algae_processor_teleop) as f64,
+            ),
+            (
+                "algae_net",
+                (data.algae_net_auto + data.algae_net_teleop) as f64,
+            ),
+        ]
+    }
+
+    fn official_totals(breakdown: &ScoreBreakdown2025) -> Vec<(&'static str, f64)> {
+        let mut totals = vec![
+            ("algae_processor", breakdown.wallAlgaeCount as f64),
+            ("algae_net", breakdown.netAlgaeCount as f64),
+        ];
+
+        //the teleop reef is the reef at the end of the match so the auto coral is taken back out
+        if let (Some(auto), Some(end)) = (&breakdown.autoReef, &breakdown.teleopReef) {
+            let auto_levels = auto.levels();
+            let end_levels = end.levels();
+            let names = [
+                ("coral_l1_auto", "coral_l1_teleop"),
+                ("coral_l2_auto", "coral_l2_teleop"),
+                ("coral_l3_auto", "coral_l3_teleop"),
+                ("coral_l4_auto", "coral_l4_teleop"),
+            ];
+            for (level, (auto_name, teleop_name)) in names.into_iter().enumerate() {
+                totals.push((auto_name, auto_levels[level] as f64));
+                totals.push((
+                    teleop_name,
+                    end_levels[level].saturating_sub(auto_levels[level]) as f64,
+                ));
+            }
+        }
+
+        totals
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct MatchData2025 {
+    ///left the starting line in auto
+    pub leave: bool,
+    pub coral_l1_auto: u32,
+    pub coral_l2_auto: u32,
+    pub coral_l3_auto: u32,
+    pub coral_l4_auto: u32,
+    pub algae_processor_auto: u32,
+    pub algae_net_auto: u32,
+
+    pub coral_l1_teleop: u32,
+    pub coral_l2_teleop: u32,
+    pub coral_l3_teleop: u32,
+    pub coral_l4_teleop: u32,
+    pub algae_processor_teleop: u32,
+    pub algae_net_teleop: u32,
+
+    pub endgame: BargeEndgame,
+}
+
+impl MatchData2025 {
+    ///What the robot scored by itself going by the 2025 manual. Algae in the processor also gives
+    ///the other alliance a shot at the net, that is not counted here.
+    pub fn points(&self) -> u32 {
+        let auto = self.leave as u32 * 3
+            + self.coral_l1_auto * 3
+            + self.coral_l2_auto * 4
+            + self.coral_l3_auto * 6
+            + self.coral_l4_auto * 7;
+        let teleop = self.coral_l1_teleop * 2
+            + self.coral_l2_teleop * 3
+            + self.coral_l3_teleop * 4
+            + self.coral_l4_teleop * 5;
+        let algae = (self.algae_processor_auto + self.algae_processor_teleop) * 6
+            + (self.algae_net_auto + self.algae_net_teleop) * 4;
+
+        auto + teleop + algae + self.endgame.points()
+    }
+}
+
+///The aggregate of a set of MatchData2025, one entry per field
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct MatchData2025Avg {
+    ///the mean is the fraction of matches they left in
+    pub leave: NumericStats,
+    pub coral_l1_auto: NumericStats,
+    pub coral_l2_auto: NumericStats,
+    pub coral_l3_auto: NumericStats,
+    pub coral_l4_auto: NumericStats,
+    pub algae_processor_auto: NumericStats,
+    pub algae_net_auto: NumericStats,
+    pub coral_l1_teleop: NumericStats,
+    pub coral_l2_teleop: NumericStats,
+    pub coral_l3_teleop: NumericStats,
+    pub coral_l4_teleop: NumericStats,
+    pub algae_processor_teleop: NumericStats,
+    pub algae_net_teleop: NumericStats,
+    pub endgame: EnumStats<BargeEndgame>,
+    ///see MatchData2025::points
+    pub points: NumericStats,
+}
+
+///What the robot can do, from asking the team in the pits
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub struct PitData2025 {
+    drivebase: Drivebase,
+
+    coral_ground_intake: bool,
+    coral_station_intake: bool,
+    ///which reef levels it can put coral on
+    coral_l1: bool,
+    coral_l2: bool,
+    coral_l3: bool,
+    coral_l4: bool,
+
+    algae_ground_intake: bool,
+    ///can knock or pick algae off the reef
+    algae_reef_removal: bool,
+    processor: bool,
+    net: bool,
+
+    posible_endgame: BargeEndgame,
+
+    can_leave_auto: bool,
+    expected_coral_auto: u32,
+}
+
+// yearly support enums, do not use outside of team match report.
+//not just Endgame so the openapi schema does not clash with the 2024 one
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub enum BargeEndgame {
+    DeepCage,
+    ShallowCage,
+    Park,
+    None,
+}
+
+impl BargeEndgame {
+    pub fn points(&self) -> u32 {
+        match self {
+            BargeEndgame::DeepCage => 12,
+            BargeEndgame::ShallowCage => 6,
+            BargeEndgame::Park => 2,
+            BargeEndgame::None => 0,
+        }
+    }
+}
+
+///Where TBA says a robot ended the match
+#[derive(Deserialize, Serialize, ToSchema, Debug, Clone)]
+pub enum BargeResult2025 {
+    None,
+    Parked,
+    ShallowCage,
+    DeepCage,
+}
+
+///Which branches of one reef level have coral on them, A to L going around the reef
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ReefRow2025 {
+    nodeA: bool,
+    nodeB: bool,
+    nodeC: bool,
+    nodeD: bool,
+    nodeE: bool,
+    nodeF: bool,
+    nodeG: bool,
+    nodeH: bool,
+    nodeI: bool,
+    nodeJ: bool,
+    nodeK: bool,
+    nodeL: bool,
+}
+
+///The reef at the end of auto or the end of the match
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct Reef2025 {
+    topRow: ReefRow2025,
+    midRow: ReefRow2025,
+    botRow: ReefRow2025,
+    ///coral in the trough (L1), it has no branches
+    trough: u32,
+    tba_topRowCount: u32,
+    tba_midRowCount: u32,
+    tba_botRowCount: u32,
+}
+
+impl Reef2025 {
+    ///Coral on L1 to L4
+    fn levels(&self) -> [u32; 4] {
+        [
+            self.trough,
+            self.tba_botRowCount,
+            self.tba_midRowCount,
+            self.tba_topRowCount,
+        ]
+    }
+}
+
+///One alliance's `score_breakdown` from TBA for a 2025 match. Anything TBA left out is 0 (or
+///None for the per robot fields and the reef).
+#[allow(nonstandard_style)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ScoreBreakdown2025 {
+    totalPoints: u32,
+    autoPoints: u32,
+    teleopPoints: u32,
+    adjustPoints: u32,
+    foulPoints: u32,
+    foulCount: u32,
+    techFoulCount: u32,
+    rp: u32,
+
+    //per robot, in driver station order
+    autoLineRobot1: Option<AutoLine>,
+    autoLineRobot2: Option<AutoLine>,
+    autoLineRobot3: Option<AutoLine>,
+    endGameRobot1: Option<BargeResult2025>,
+    endGameRobot2: Option<BargeResult2025>,
+    endGameRobot3: Option<BargeResult2025>,
+
+    autoMobilityPoints: u32,
+    autoCoralCount: u32,
+    autoCoralPoints: u32,
+    autoReef: Option<Reef2025>,
+
+    teleopCoralCount: u32,
+    teleopCoralPoints: u32,
+    teleopReef: Option<Reef2025>,
+
+    ///algae in the processor
+    wallAlgaeCount: u32,
+    netAlgaeCount: u32,
+    algaePoints: u32,
+
+    endGameBargePoints: u32,
+
+    autoBonusAchieved: bool,
+    coralBonusAchieved: bool,
+    bargeBonusAchieved: bool,
+    coopertitionCriteriaMet: bool,
+
+    g206Penalty: bool,
+    g410Penalty: bool,
+    g418Penalty: bool,
+    g428Penalty: bool,
+}
+
+impl ScoreBreakdown2025 {
+    pub fn robot_leave(&self, station: usize) -> Option<&AutoLine> {
+        [
+            &self.autoLineRobot1,
+            &self.autoLineRobot2,
+            &self.autoLineRobot3,
+        ]
+        .get(station)?
+        .as_ref()
+    }
+
+    pub fn robot_endgame(&self, station: usize) -> Option<&BargeResult2025> {
+        [
+            &self.endGameRobot1,
+            &self.endGameRobot2,
+            &self.endGameRobot3,
+        ]
+        .get(station)?
+        .as_ref()
+    }
+}
+
+impl ScoreBreakdown for ScoreBreakdown2025 {
+    fn from_totals(total: Option<u32>, auto: Option<u32>, foul: Option<u32>) -> Self {
+        let (total, auto, foul) = (total.unwrap_or(0), auto.unwrap_or(0), foul.unwrap_or(0));
+        Self {
+            totalPoints: total,
+            autoPoints: auto,
+            teleopPoints: total.saturating_sub(auto + foul),
+            foulPoints: foul,
+            ..Default::default()
+        }
+    }
+}
+
+///A report by `recording_team_number` on team 4 in the first qualifier at 2025casj where the robot
+///only parked, tests change whatever they are about
+#[cfg(test)]
+pub fn test_report(
+    recording_team_number: u32,
+    team_member: &str,
+) -> super::super::TeamMatchReport<Reefscape> {
+    serde_json::from_value(serde_json::json!({
+        "team_number": 4,
+        "recording_team_number": recording_team_number,
+        "team_member": team_member,
+        "event": "2025casj",
+        "match_number": {"number": 1, "level": "Qualifier"},
+        "notes": "",
+        "data": {
+            "leave": false,
+            "coral_l1_auto": 0, "coral_l2_auto": 0, "coral_l3_auto": 0, "coral_l4_auto": 0,
+            "algae_processor_auto": 0, "algae_net_auto": 0,
+            "coral_l1_teleop": 0, "coral_l2_teleop": 0, "coral_l3_teleop": 0,
+            "coral_l4_teleop": 0, "algae_processor_teleop": 0, "algae_net_teleop": 0,
+            "endgame": "Park",
+        },
+        "team_spesific_data": null,
+        "timestamp": 0,
+    }))
+    .unwrap()
+}
@@ -0,0 +1,377 @@
+//! Bearer tokens. A key is traded for a token at `/login` and the token goes in the
+//! `Authorization` header from then on. Tokens are HS256 JWTs so any client library can read
+//! them, only the server can make them.
+//! The old `id` and `key` headers still work so tablets that have not been updated keep going.
+
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use anyhow::*;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::warn;
+use rand::RngCore;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::ToSchema;
+
+use super::{
+    caller_team,
+    openscout::{AuthLevel, Permission},
+    season::Season,
+    store::Viewer,
+    DataManager,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+///Who made a request, from their token or their key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Caller {
+    pub team: u32,
+    pub key_id: String,
+    ///the name of the key, for a scout that is who they are
+    pub name: String,
+    pub auth: AuthLevel,
+}
+
+///What is signed into a token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub team: u32,
+    ///the key the token was made with, a revoked key takes its tokens with it
+    pub kid: String,
+    pub auth: AuthLevel,
+    ///when the key was made or last rotated, tokens from before a rotation are no good
+    pub key_created: u64,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    pub team: u32,
+    ///a key from `/keys/{team_num}` or the team's default key
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Session {
+    ///goes in the `Authorization` header as `Bearer {token}`
+    pub token: String,
+    pub token_type: String,
+    ///unix epoch
+    pub expires: u64,
+}
+
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+///Signs and checks tokens. Shared by every season so a token works on all of them.
+#[derive(Clone)]
+pub struct SessionSigner {
+    secret: Arc<Vec<u8>>,
+    lifetime: Duration,
+}
+
+impl SessionSigner {
+    ///Without a secret a random one is made, every token dies when the server restarts then
+    pub fn new(secret: Option<String>, lifetime: Duration) -> Self {
+        let secret = match secret {
+            Some(secret) => secret.into_bytes(),
+            None => {
+                warn!(
+                    "no token_secret in the config, everyone has to log in again after a restart"
+                );
+                let mut secret = vec![0; 32];
+                rand::thread_rng().fill_bytes(&mut secret);
+                secret
+            }
+        };
+        Self {
+            secret: Arc::new(secret),
+            lifetime,
+        }
+    }
+
+    pub fn sign(&self, caller: &Caller, key_created: u64) -> Result<Session> {
+        let iat = Utc::now().timestamp() as u64;
+        let claims = Claims {
+            team: caller.team,
+            kid: caller.key_id.clone(),
+            auth: caller.auth.clone(),
+            key_created,
+            iat,
+            exp: iat + self.lifetime.as_secs(),
+        };
+
+        let body = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(HEADER),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?)
+        );
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&body)?.finalize().into_bytes());
+
+        Ok(Session {
+            token: format!("{}.{}", body, signature),
+            token_type: "Bearer".to_string(),
+            expires: claims.exp,
+        })
+    }
+
+    ///The claims of a token this server signed that has not expired yet
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        let unauthorized = || anyhow!(StatusCode::UNAUTHORIZED);
+
+        let (body, signature) = token.rsplit_once('.').ok_or_else(unauthorized)?;
+        let (header, claims) = body.split_once('.').ok_or_else(unauthorized)?;
+        //only the one algorithm is ever made so anything else is not ours
+        if URL_SAFE_NO_PAD.decode(header).map_err(|_| unauthorized())? != HEADER.as_bytes() {
+            return Err(unauthorized());
+        }
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| unauthorized())?;
+        self.mac(body)?
+            .verify_slice(&signature)
+            .map_err(|_| unauthorized())?;
+
+        let claims: Claims =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).map_err(|_| unauthorized())?)?;
+        if claims.exp <= Utc::now().timestamp() as u64 {
+            return Err(unauthorized());
+        }
+        Ok(claims)
+    }
+
+    fn mac(&self, body: &str) -> Result<HmacSha256> {
+        let mut mac = HmacSha256::new_from_slice(&self.secret)
+            .map_err(|e| anyhow!("unable to use the token secret: {}", e))?;
+        mac.update(body.as_bytes());
+        Ok(mac)
+    }
+}
+
+///The bearer token from the `Authorization` header, none when there is not one
+pub fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+///What a route needs from the caller
+pub trait Requirement {
+    const PERMISSION: Permission;
+}
+
+macro_rules! requirement {
+    ($($permission:ident),*) => {
+        $(
+            pub struct $permission;
+            impl Requirement for $permission {
+                const PERMISSION: Permission = Permission::$permission;
+            }
+        )*
+    };
+}
+
+requirement!(
+    ReadData,
+    PostReports,
+    ScoutMatches,
+    ManageScouts,
+    ManageKeys,
+    ManageServer,
+    ChangeOwnKey
+);
+
+///Put this in a handler's arguments and the request is turned away before the handler runs
+///unless the caller's role has the permission `R` names, eg. `_: Authorized<ReadData>`
+pub struct Authorized<R> {
+    ///none when auth is off
+    pub caller: Option<Caller>,
+    team: Option<u32>,
+    requirement: PhantomData<R>,
+}
+
+impl<R> Authorized<R> {
+    ///The team the caller is from. With auth off that is whatever is in the `id` header.
+    pub fn team(&self) -> Result<u32> {
+        self.team.ok_or(anyhow!(StatusCode::BAD_REQUEST))
+    }
+
+    ///Whose reports the caller gets to see. Admins and everyone with auth off see them all.
+    pub fn viewer(&self) -> Viewer {
+        match &self.caller {
+            Some(caller) if caller.auth != AuthLevel::ADMIN => Viewer::Team(caller.team),
+            _ => Viewer::All,
+        }
+    }
+
+    ///Only an admin can act for another team, anyone else posting as someone else's team would
+    ///get to own their reports and skew their scouting accuracy. Scouts also only get to act as
+    ///themselves, their key is named after them. Everyone else can act for any scout on their
+    ///team.
+    pub fn check_scout(&self, team: u32, scout: &str) -> Result<()> {
+        match &self.caller {
+            Some(caller) if caller.auth == AuthLevel::ADMIN => Ok(()),
+            Some(caller)
+                if caller.team != team
+                    || (caller.auth == AuthLevel::SCOUT && caller.name != scout) =>
+            {
+                Err(anyhow!(StatusCode::FORBIDDEN))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Season, R: Requirement> FromRequestParts<DataManager<S>> for Authorized<R> {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        dm: &DataManager<S>,
+    ) -> Result<Self, Self::Rejection> {
+        let caller = dm.authorize(parts, R::PERMISSION).await.map_err(|e| {
+            match e.downcast_ref::<StatusCode>() {
+                Some(status) => (*status, status.to_string()),
+                None => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            }
+        })?;
+        let team = match &caller {
+            Some(caller) => Some(caller.team),
+            None => caller_team(&parts.headers).ok(),
+        };
+
+        Result::Ok(Self {
+            caller,
+            team,
+            requirement: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    fn signer() -> SessionSigner {
+        SessionSigner::new(Some("secret".to_string()), Duration::from_secs(60))
+    }
+
+    fn caller() -> Caller {
+        Caller {
+            team: 254,
+            key_id: "abcdefabcdef".to_string(),
+            name: "alice".to_string(),
+            auth: AuthLevel::SCOUT,
+        }
+    }
+
+    fn rejected(result: Result<Claims>) -> bool {
+        result.is_err_and(|e| e.downcast_ref::<StatusCode>() == Some(&StatusCode::UNAUTHORIZED))
+    }
+
+    #[test]
+    fn signed_token_verifies() {
+        let session = signer().sign(&caller(), 7).unwrap();
+        let claims = signer().verify(&session.token).unwrap();
+        assert_eq!(claims.team, 254);
+        assert_eq!(claims.kid, "abcdefabcdef");
+        assert_eq!(claims.auth, AuthLevel::SCOUT);
+        assert_eq!(claims.key_created, 7);
+        assert_eq!(claims.exp, session.expires);
+    }
+
+    #[test]
+    fn tampered_signature_is_rejected() {
+        let token = signer().sign(&caller(), 0).unwrap().token;
+        let (body, signature) = token.rsplit_once('.').unwrap();
+        let flipped = if signature.starts_with('A') { "B" } else { "A" };
+        let tampered = format!("{}.{}{}", body, flipped, &signature[1..]);
+        assert!(rejected(signer().verify(&tampered)));
+    }
+
+    #[test]
+    fn tampered_claims_are_rejected() {
+        let token = signer().sign(&caller(), 0).unwrap().token;
+        let parts: Vec<&str> = token.split('.').collect();
+        let mut claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        claims["auth"] = serde_json::json!("ADMIN");
+        let forged = format!(
+            "{}.{}.{}",
+            parts[0],
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap()),
+            parts[2]
+        );
+        assert!(rejected(signer().verify(&forged)));
+    }
+
+    #[test]
+    fn other_secret_is_rejected() {
+        let token = signer().sign(&caller(), 0).unwrap().token;
+        let other = SessionSigner::new(Some("other".to_string()), Duration::from_secs(60));
+        assert!(rejected(other.verify(&token)));
+    }
+
+    #[test]
+    fn wrong_header_is_rejected() {
+        let token = signer().sign(&caller(), 0).unwrap().token;
+        let (_, rest) = token.split_once('.').unwrap();
+        let none = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        assert!(rejected(signer().verify(&format!("{}.{}", none, rest))));
+
+        //signed properly but with a header that is not ours
+        let (claims, _) = rest.split_once('.').unwrap();
+        let body = format!("{}.{}", none, claims);
+        let signature =
+            URL_SAFE_NO_PAD.encode(signer().mac(&body).unwrap().finalize().into_bytes());
+        assert!(rejected(
+            signer().verify(&format!("{}.{}", body, signature))
+        ));
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let expired = SessionSigner::new(Some("secret".to_string()), Duration::from_secs(0));
+        let token = expired.sign(&caller(), 0).unwrap().token;
+        assert!(rejected(expired.verify(&token)));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!(rejected(signer().verify("")));
+        assert!(rejected(signer().verify("a.b")));
+        assert!(rejected(signer().verify("not base64.at all.!")));
+    }
+
+    #[test]
+    fn bearer_token_from_header() {
+        let (parts, _) = Request::builder()
+            .header(AUTHORIZATION, "Bearer abc.def.ghi")
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert_eq!(bearer_token(&parts), Some("abc.def.ghi"));
+
+        let (parts, _) = Request::builder()
+            .header(AUTHORIZATION, "Basic abc")
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert_eq!(bearer_token(&parts), None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Where schedules and results come from.
+//! TBA and the FRC Events API both implement [`EventSource`] and [`EventSources`] tries them in
+//! the order set in the config, so the rest of the server does not care which one answered.
+//! Sources that are down are skipped until they come back, see [`SourceHealth`].
+
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use anyhow::*;
+use async_trait::async_trait;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{health::SourceHealth, season::Season, theblueallience::TbaMatchData, MatchNumber};
+
+///Implemented for every season, only the score breakdowns depend on it
+#[async_trait]
+pub trait EventSource<S: Season>: Send + Sync {
+    ///Used in the logs and the source order in the config
+    fn name(&self) -> &'static str;
+
+    async fn get_match_data(
+        &self,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TbaMatchData<S>>;
+
+    ///Every match at the event, played or not
+    async fn get_match_data_list(&self, event: String) -> Result<Vec<TbaMatchData<S>>>;
+
+    async fn get_rankings(&self, event: String) -> Result<Vec<Ranking>>;
+
+    ///The team numbers of every team at the event
+    async fn get_team_list(&self, event: String) -> Result<Vec<u32>>;
+}
+
+///A team's place in the qualification rankings
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Ranking {
+    pub rank: u32,
+    pub team_number: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub matches_played: u32,
+    ///the first sort order, the average ranking points for recent games
+    pub ranking_score: f64,
+}
+
+type SourceFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+///Every configured source in the order they should be asked
+#[derive(Clone)]
+pub struct EventSources<S: Season> {
+    sources: Vec<Arc<dyn EventSource<S>>>,
+    health: SourceHealth,
+}
+
+impl<S: Season> EventSources<S> {
+    pub fn new(sources: Vec<Arc<dyn EventSource<S>>>, health: SourceHealth) -> Result<Self> {
+        if sources.is_empty() {
+            return Err(anyhow!("at least one schedule source is needed"));
+        }
+        Ok(Self { sources, health })
+    }
+
+    ///Asks each source in turn and gives back the first answer, the error has every source's
+    async fn first_ok<T>(
+        &self,
+        request: impl for<'a> Fn(&'a dyn EventSource<S>) -> SourceFuture<'a, T>,
+    ) -> Result<T> {
+        let mut errors = Vec::new();
+        for source in self.sources.iter() {
+            match self
+                .health
+                .call(source.name(), request(source.as_ref()))
+                .await
+            {
+                Result::Ok(data) => return Ok(data),
+                Err(e) => {
+                    warn!(
+                        "{} request failed, trying the next source: {}",
+                        source.name(),
+                        e
+                    );
+                    errors.push(format!("{}: {}", source.name(), e));
+                }
+            }
+        }
+        Err(anyhow!(
+            "no schedule source answered ({})",
+            errors.join(", ")
+        ))
+    }
+}
+
+#[async_trait]
+impl<S: Season> EventSource<S> for EventSources<S> {
+    fn name(&self) -> &'static str {
+        "schedule"
+    }
+
+    async fn get_match_data(
+        &self,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TbaMatchData<S>> {
+        self.first_ok(|source| source.get_match_data(match_number.clone(), event.clone()))
+            .await
+    }
+
+    async fn get_match_data_list(&self, event: String) -> Result<Vec<TbaMatchData<S>>> {
+        self.first_ok(|source| source.get_match_data_list(event.clone()))
+            .await
+    }
+
+    async fn get_rankings(&self, event: String) -> Result<Vec<Ranking>> {
+        self.first_ok(|source| source.get_rankings(event.clone()))
+            .await
+    }
+
+    async fn get_team_list(&self, event: String) -> Result<Vec<u32>> {
+        self.first_ok(|source| source.get_team_list(event.clone()))
+            .await
+    }
+}
@@ -0,0 +1,562 @@
+//! SQLite backend, for running the server off a single file without a database daemon.
+//! Reports are stored as json next to the columns used for lookups. Every row is tagged with the
+//! season it was written in so replacing the season structs never breaks older data.
+
+use std::str::FromStr;
+
+use anyhow::*;
+use async_trait::async_trait;
+use log::{info, warn};
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool},
+    QueryBuilder, Row, Sqlite,
+};
+
+use super::{
+    keys::ApiKey,
+    openscout::Auth,
+    season::Season,
+    store::{ScoutStore, Shared, Viewer},
+    MatchNumber, TeamMatchReport, TeamPitReport,
+};
+
+///One database holds every season, the rows are told apart by their season column
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    ///Opens (or creates) the database and runs any migrations that have not been applied yet.
+    ///`url` is in the sqlx format, eg. `sqlite:///var/lib/openscout.db`
+    pub async fn new(url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await?;
+
+        sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+        let store = Self { pool };
+        store.hash_legacy_keys().await?;
+        info!("sqlite database ready at {}", url);
+
+        Ok(store)
+    }
+
+    ///Moves the plaintext keys from before keys were hashed over to api_keys
+    async fn hash_legacy_keys(&self) -> Result<()> {
+        let rows = sqlx::query("SELECT team, key, auth FROM auth")
+            .fetch_all(&self.pool)
+            .await?;
+        for row in rows {
+            let auth = Auth {
+                _id: row.try_get("team")?,
+                key: row.try_get("key")?,
+                auth: serde_json::from_str(row.try_get("auth")?)?,
+            };
+            let team = auth._id;
+            //argon2 is slow on purpose, a big auth table would hold up the runtime
+            let key = tokio::task::spawn_blocking(move || ApiKey::from_legacy(auth)).await??;
+            self.write_key(&key).await?;
+            sqlx::query("DELETE FROM auth WHERE team = ?")
+                .bind(team)
+                .execute(&self.pool)
+                .await?;
+            info!("hashed the key for team {}", team);
+        }
+        Ok(())
+    }
+
+    async fn write_key(&self, key: &ApiKey) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO api_keys (id, team, key) VALUES (?, ?, ?)")
+            .bind(&key._id)
+            .bind(key.team)
+            .bind(to_json(key)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    ///Runs a lookup against one of the report tables.
+    ///Returns `StatusCode::NO_CONTENT` when nothing matches like the other backends.
+    async fn find<T: DeserializeOwned + Shared>(
+        &self,
+        season: u32,
+        table: Table,
+        filter: Filter<'_>,
+    ) -> Result<Vec<T>> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT report FROM ");
+        query.push(table.name());
+        query.push(" WHERE season = ").push_bind(season);
+        query
+            .push(" AND team_number = ")
+            .push_bind(filter.team_number);
+        query.push(" AND event = ").push_bind(filter.event);
+
+        if let Some(recording_team) = filter.recording_team {
+            query
+                .push(format!(" AND {} = ", table.recording_team_column()))
+                .push_bind(recording_team);
+        }
+        if let Some(team_member) = filter.team_member {
+            query.push(" AND team_member = ").push_bind(team_member);
+        }
+        if let Some(match_number) = filter.match_number {
+            query
+                .push(" AND match_level = ")
+                .push_bind(match_number.level.name())
+                .push(" AND match_number = ")
+                .push_bind(match_number.number);
+        }
+        if let Viewer::Team(team) = filter.viewer {
+            //reports from before there was a visibility are public
+            query
+                .push(format!(" AND ({} = ", table.recording_team_column()))
+                .push_bind(team)
+                .push(" OR coalesce(json_extract(report, '$.visibility'), 'Public') = 'Public'")
+                .push(" OR EXISTS (SELECT 1 FROM json_each(report, '$.visibility.Shared') WHERE value = ")
+                .push_bind(team)
+                .push("))");
+        }
+
+        query.push(table.newest_first());
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        if rows.is_empty() {
+            return Err(anyhow!(StatusCode::NO_CONTENT));
+        }
+
+        let mut data = Vec::new();
+        for row in rows.iter() {
+            //one bad row should not take every other report for the team down with it
+            let report: T = match serde_json::from_str(row.try_get("report")?) {
+                Result::Ok(report) => report,
+                Err(e) => {
+                    warn!(
+                        "skipping a {} report for team {} at {} that does not parse: {}",
+                        season, filter.team_number, filter.event, e
+                    );
+                    continue;
+                }
+            };
+            data.extend(report.for_viewer(filter.viewer));
+            //not a LIMIT 1 in the query, the newest row might be one that does not parse
+            if filter.newest_only && !data.is_empty() {
+                break;
+            }
+        }
+        if data.is_empty() {
+            return Err(anyhow!(StatusCode::NO_CONTENT));
+        }
+        Ok(data)
+    }
+
+    async fn find_one<T: DeserializeOwned + Shared>(
+        &self,
+        season: u32,
+        table: Table,
+        filter: Filter<'_>,
+    ) -> Result<T> {
+        self.find(
+            season,
+            table,
+            Filter {
+                newest_only: true,
+                ..filter
+            },
+        )
+        .await?
+        .pop()
+        .ok_or(anyhow!(StatusCode::NO_CONTENT))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Table {
+    Match,
+    Pit,
+}
+
+impl Table {
+    fn name(&self) -> &'static str {
+        match self {
+            Table::Match => "match_reports",
+            Table::Pit => "pit_reports",
+        }
+    }
+
+    //the two report structs do not agree on what to call this
+    fn recording_team_column(&self) -> &'static str {
+        match self {
+            Table::Match => "recording_team_number",
+            Table::Pit => "recording_team",
+        }
+    }
+
+    fn newest_first(&self) -> &'static str {
+        match self {
+            Table::Match => " ORDER BY timestamp DESC, id DESC",
+            Table::Pit => " ORDER BY id DESC",
+        }
+    }
+}
+
+///What a report lookup filters on, `None` fields are not checked.
+#[derive(Clone, Copy)]
+struct Filter<'a> {
+    viewer: Viewer,
+    team_number: u32,
+    event: &'a str,
+    match_number: Option<&'a MatchNumber>,
+    recording_team: Option<u32>,
+    team_member: Option<&'a str>,
+    newest_only: bool,
+}
+
+impl<'a> Filter<'a> {
+    fn new(viewer: Viewer, team_number: u32, event: &'a str) -> Self {
+        Self {
+            viewer,
+            team_number,
+            event,
+            match_number: None,
+            recording_team: None,
+            team_member: None,
+            newest_only: false,
+        }
+    }
+}
+
+fn to_json<T: Serialize>(data: &T) -> Result<String> {
+    Ok(serde_json::to_string(data)?)
+}
+
+#[async_trait]
+impl<S: Season> ScoutStore<S> for SqliteStore {
+    async fn post_team_match_data(&self, data: TeamMatchReport<S>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO match_reports (season, team_number, recording_team_number, team_member, \
+             event, match_level, match_number, timestamp, report) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(S::YEAR)
+        .bind(data.team_number)
+        .bind(data.recording_team_number)
+        .bind(&data.team_member)
+        .bind(&data.event)
+        .bind(data.match_number.level.name())
+        .bind(data.match_number.number)
+        .bind(data.timestamp as i64)
+        .bind(to_json(&data)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn post_team_pit_data(&self, data: TeamPitReport<S>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO pit_reports (season, team_number, recording_team, team_member, event, \
+             report) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(S::YEAR)
+        .bind(data.team_number)
+        .bind(data.recording_team)
+        .bind(&data.team_member)
+        .bind(&data.event)
+        .bind(to_json(&data)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_last_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_one(
+            S::YEAR,
+            Table::Match,
+            Filter {
+                match_number: Some(&match_number),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_all_team_match_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find(
+            S::YEAR,
+            Table::Match,
+            Filter {
+                match_number: Some(&match_number),
+                recording_team: Some(recording_team),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_team_match_data_by_induvidual(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        recording_induvidual: String,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_one(
+            S::YEAR,
+            Table::Match,
+            Filter {
+                match_number: Some(&match_number),
+                recording_team: Some(recording_team),
+                team_member: Some(&recording_induvidual),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_last_team_match_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>> {
+        self.find_one(
+            S::YEAR,
+            Table::Match,
+            Filter {
+                match_number: Some(&match_number),
+                recording_team: Some(recording_team),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_all_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find(
+            S::YEAR,
+            Table::Match,
+            Filter {
+                match_number: Some(&match_number),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_all_team_event_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>> {
+        self.find(
+            S::YEAR,
+            Table::Match,
+            Filter::new(viewer, team_number, &event),
+        )
+        .await
+    }
+
+    async fn get_last_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_one(
+            S::YEAR,
+            Table::Pit,
+            Filter::new(viewer, team_number, &event),
+        )
+        .await
+    }
+
+    async fn get_all_team_pit_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        event: String,
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.find(
+            S::YEAR,
+            Table::Pit,
+            Filter {
+                recording_team: Some(recording_team),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_team_pit_data_by_induvidual(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        recording_induvidual: String,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_one(
+            S::YEAR,
+            Table::Pit,
+            Filter {
+                recording_team: Some(recording_team),
+                team_member: Some(&recording_induvidual),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_last_team_pit_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>> {
+        self.find_one(
+            S::YEAR,
+            Table::Pit,
+            Filter {
+                recording_team: Some(recording_team),
+                ..Filter::new(viewer, team_number, &event)
+            },
+        )
+        .await
+    }
+
+    async fn get_all_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamPitReport<S>>> {
+        self.find(
+            S::YEAR,
+            Table::Pit,
+            Filter::new(viewer, team_number, &event),
+        )
+        .await
+    }
+
+    async fn get_key(&self, id: &str) -> Result<ApiKey> {
+        let row = sqlx::query("SELECT key FROM api_keys WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(anyhow!(StatusCode::UNAUTHORIZED))?;
+        Ok(serde_json::from_str(row.try_get("key")?)?)
+    }
+
+    async fn get_team_keys(&self, team: u32) -> Result<Vec<ApiKey>> {
+        let rows = sqlx::query("SELECT key FROM api_keys WHERE team = ? ORDER BY rowid")
+            .bind(team)
+            .fetch_all(&self.pool)
+            .await?;
+        let mut keys = Vec::new();
+        for row in rows {
+            keys.push(serde_json::from_str(row.try_get("key")?)?);
+        }
+        Ok(keys)
+    }
+
+    async fn get_all_keys(&self) -> Result<Vec<ApiKey>> {
+        let rows = sqlx::query("SELECT key FROM api_keys ORDER BY team, rowid")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut keys = Vec::new();
+        for row in rows {
+            keys.push(serde_json::from_str(row.try_get("key")?)?);
+        }
+        Ok(keys)
+    }
+
+    async fn put_key(&self, key: ApiKey) -> Result<()> {
+        self.write_key(&key).await
+    }
+
+    async fn delete_key(&self, id: &str) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM api_keys WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(anyhow!(StatusCode::NOT_FOUND));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            season::reefscape::{test_report, Reefscape},
+            Complevel,
+        },
+        *,
+    };
+
+    #[tokio::test]
+    async fn newest_report_that_parses_wins() {
+        let path = std::env::temp_dir().join(format!("openscout-sqlite-{}.db", std::process::id()));
+        let store = SqliteStore::new(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap();
+
+        let mut report = test_report(254, "alice");
+        report.notes = "parses".to_string();
+        ScoutStore::<Reefscape>::post_team_match_data(&store, report)
+            .await
+            .unwrap();
+        //newer than the good one so it comes up first
+        sqlx::query(
+            "INSERT INTO match_reports (season, team_number, recording_team_number, team_member, \
+             event, match_level, match_number, timestamp, report) \
+             VALUES (2025, 4, 254, 'bob', '2025casj', 'Qualifier', 1, 100, '{not json')",
+        )
+        .execute(&store.pool)
+        .await
+        .unwrap();
+
+        let newest = ScoutStore::<Reefscape>::get_last_team_match_data(
+            &store,
+            Viewer::All,
+            4,
+            MatchNumber {
+                level: Complevel::Qualifier,
+                number: 1,
+            },
+            "2025casj".to_string(),
+        )
+        .await;
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(newest.unwrap().notes, "parses");
+    }
+}
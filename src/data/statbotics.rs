@@ -1,35 +1,49 @@
 use anyhow::*;
 use serde::*;
 
-use super::{Allience, MatchNumber};
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+use super::{cache::HttpCache, Allience, MatchNumber};
+
+//statbotics only recomputes after matches are played
+const TEAM_TTL: Duration = Duration::from_secs(10 * 60);
+const MATCH_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
 pub struct Statbotics {
     client: reqwest::Client,
+    cache: HttpCache,
 }
 
 impl Statbotics {
-    pub async fn new() -> Result<Self> {
-        let client = reqwest::Client::new();
-        client
+    pub fn new(cache: HttpCache) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            cache,
+        }
+    }
+
+    ///Checks if the statbotics api is working
+    pub async fn check(&self) -> Result<()> {
+        self.client
             .get("https://api.statbotics.io/v3/")
             .send()
             .await?
             .error_for_status()?;
-
-        Ok(Self { client })
+        Ok(())
     }
 
-    pub async fn get_team_data(&self, team_num: u32) -> Result<StatboticsTeamData> {
+    ///The team's EPA for the season, `year` is the season's year
+    pub async fn get_team_data(&self, team_num: u32, year: u32) -> Result<StatboticsTeamData> {
         let epa_request = self
-            .client
-            .get(format!("https://api.statbotics.io/v3/team_year/{}/2024", {
-                team_num
-            }))
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<StatboticsTeamData>()
+            .cache
+            .get_json::<StatboticsTeamData>(
+                self.client.get(format!(
+                    "https://api.statbotics.io/v3/team_year/{}/{}",
+                    team_num, year
+                )),
+                TEAM_TTL,
+            )
             .await?;
 
         Ok(epa_request)
@@ -43,15 +57,14 @@ impl Statbotics {
         let event_match = format!("{}_{}", event, match_number.get_tba_string()?);
 
         let request = self
-            .client
-            .get(format!(
-                "https://api.statbotics.io/v3/match/{}",
-                event_match
-            ))
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<StatboticsMatchData>()
+            .cache
+            .get_json::<StatboticsMatchData>(
+                self.client.get(format!(
+                    "https://api.statbotics.io/v3/match/{}",
+                    event_match
+                )),
+                MATCH_TTL,
+            )
             .await?;
 
         Ok(request)
@@ -0,0 +1,487 @@
+//! Season independent helpers for summarising scouted values.
+//! The season structs use these to build their aggregates so the math only lives in one place.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::MatchNumber;
+
+///Summary of one numeric field over a set of reports
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct NumericStats {
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    ///population standard deviation
+    pub std_dev: f64,
+}
+
+impl NumericStats {
+    ///All zeros when there are no values
+    pub fn new(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let count = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / count;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+
+        let middle = sorted.len() / 2;
+        let median = match sorted.len() % 2 {
+            0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+            _ => sorted[middle],
+        };
+
+        Self {
+            mean,
+            median,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            std_dev: variance.sqrt(),
+        }
+    }
+
+    ///Shorthand for pulling a field out of every report
+    pub fn of<T>(data: &[T], field: impl Fn(&T) -> f64) -> Self {
+        Self::new(&data.iter().map(field).collect::<Vec<f64>>())
+    }
+}
+
+///Summary of an enum field, the distribution is keyed by the variant name
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EnumStats<T: ToSchema> {
+    ///The most common value, ties go to whichever got there first
+    pub mode: Option<T>,
+    pub distribution: HashMap<String, u32>,
+}
+
+impl<T: ToSchema + Serialize + Clone> EnumStats<T> {
+    pub fn new<'a>(values: impl IntoIterator<Item = &'a T>) -> Self
+    where
+        T: 'a,
+    {
+        let (mode, distribution) = tally(values);
+        Self {
+            mode: mode.cloned(),
+            distribution,
+        }
+    }
+
+    pub fn of<D>(data: &[D], field: impl Fn(&D) -> &T) -> Self {
+        Self::new(data.iter().map(field))
+    }
+}
+
+///How a field moved over the matches a team played
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldTrend {
+    pub points: Vec<TrendPoint>,
+    ///least squares slope, change per match played
+    pub slope: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrendPoint {
+    pub match_number: MatchNumber,
+    pub value: f64,
+}
+
+impl FieldTrend {
+    ///Expects the points in the order the matches were played
+    pub fn new(points: Vec<TrendPoint>) -> Self {
+        let count = points.len() as f64;
+        let mean_x = (count - 1.0) / 2.0;
+        let mean_y = points.iter().map(|p| p.value).sum::<f64>() / count;
+
+        let (covariance, variance) =
+            points
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(covariance, variance), (x, point)| {
+                    let dx = x as f64 - mean_x;
+                    (covariance + dx * (point.value - mean_y), variance + dx * dx)
+                });
+
+        Self {
+            points,
+            //a single match has no trend
+            slope: if variance > 0.0 {
+                covariance / variance
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+///Counts every value by its variant name and picks the most common one
+fn tally<'a, T: Serialize + 'a>(
+    values: impl IntoIterator<Item = &'a T>,
+) -> (Option<&'a T>, HashMap<String, u32>) {
+    let mut distribution: HashMap<String, u32> = HashMap::new();
+    let mut mode: Option<(&T, u32)> = None;
+
+    for value in values {
+        let count = distribution.entry(variant_name(value)).or_insert(0);
+        *count += 1;
+
+        if mode.is_none_or(|(_, best)| *count > best) {
+            mode = Some((value, *count));
+        }
+    }
+
+    (mode.map(|(value, _)| value), distribution)
+}
+
+///A field the scouts did not agree on, with how many of them said what
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldConflict {
+    pub field: String,
+    pub votes: HashMap<String, u32>,
+}
+
+///Majority vote on one field. Anything short of a unanimous answer is added to `conflicts`.
+///None when there is no data to vote on.
+pub fn majority<D, T: Serialize + Clone>(
+    data: &[D],
+    field_name: &str,
+    field: impl Fn(&D) -> &T,
+    conflicts: &mut Vec<FieldConflict>,
+) -> Option<T> {
+    let (winner, votes) = tally(data.iter().map(field));
+
+    if votes.len() > 1 {
+        conflicts.push(FieldConflict {
+            field: field_name.to_string(),
+            votes,
+        });
+    }
+
+    winner.cloned()
+}
+
+///The name serde gives a value, unit variants come out as just their name
+pub fn variant_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(other) => other.to_string(),
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+///A value one report had that was too far from what the rest said to count
+#[derive(Debug, Clone)]
+pub struct FieldOutlier {
+    ///index of the report in the data given to Consensus
+    pub report: usize,
+    pub field: String,
+    pub value: f64,
+    pub consensus: f64,
+}
+
+///Merges several reports on the same thing field by field, each report counting as much as its
+///weight. The season structs use it to build their consensus the same way they use `majority`
+///for the pit reports.
+pub struct Consensus<'a, D> {
+    data: &'a [D],
+    weights: &'a [f64],
+    outliers: Vec<FieldOutlier>,
+    ///how much of the weight agreed with the result, one entry per field
+    agreement: Vec<f64>,
+}
+
+impl<'a, D> Consensus<'a, D> {
+    ///`weights` goes with `data` by index
+    pub fn new(data: &'a [D], weights: &'a [f64]) -> Self {
+        Self {
+            data,
+            weights,
+            outliers: Vec::new(),
+            agreement: Vec::new(),
+        }
+    }
+
+    ///A counted field. Values too far from the weighted median are outliers and left out, the
+    ///rest are averaged by weight and rounded.
+    pub fn count(&mut self, field_name: &str, field: impl Fn(&D) -> u32) -> u32 {
+        let values: Vec<(f64, f64)> = self
+            .data
+            .iter()
+            .zip(self.weights)
+            .map(|(d, w)| (field(d) as f64, *w))
+            .collect();
+
+        let median = weighted_median(&values);
+        let spread = weighted_median(
+            &values
+                .iter()
+                .map(|(v, w)| ((v - median).abs(), *w))
+                .collect::<Vec<(f64, f64)>>(),
+        );
+        //two reports can not outvote each other, and being off by one is just scouting
+        let tolerance = (3.0 * spread).max(1.0).max(median * 0.25);
+        let is_outlier = |v: f64| values.len() >= 3 && (v - median).abs() > tolerance;
+
+        let (sum, weight) = values
+            .iter()
+            .filter(|(v, _)| !is_outlier(*v))
+            .fold((0.0, 0.0), |(sum, weight), (v, w)| {
+                (sum + v * w, weight + w)
+            });
+        let result = if weight > 0.0 {
+            (sum / weight).round()
+        } else {
+            median.round()
+        };
+
+        for (report, (value, _)) in values.iter().enumerate() {
+            if is_outlier(*value) {
+                self.outliers.push(FieldOutlier {
+                    report,
+                    field: field_name.to_string(),
+                    value: *value,
+                    consensus: result,
+                });
+            }
+        }
+
+        self.agreement.push(share(
+            values
+                .iter()
+                .map(|(v, w)| (*w, (1.0 - (v - result).abs() / result.max(1.0)).max(0.0))),
+        ));
+        result as u32
+    }
+
+    ///A field that is picked rather than counted, the value with the most weight behind it wins
+    pub fn vote<T: Serialize + Clone>(&mut self, field: impl Fn(&D) -> &T) -> T {
+        let mut votes: Vec<(String, &T, f64)> = Vec::new();
+        for (d, w) in self.data.iter().zip(self.weights) {
+            let value = field(d);
+            let name = variant_name(value);
+            match votes.iter_mut().find(|(n, _, _)| *n == name) {
+                Some((_, _, total)) => *total += w,
+                None => votes.push((name, value, *w)),
+            }
+        }
+
+        //ties go to whichever got there first like majority
+        let (winner, _, _) = votes
+            .iter()
+            .fold(None, |best: Option<&(String, &T, f64)>, vote| match best {
+                Some(best) if best.2 >= vote.2 => Some(best),
+                _ => Some(vote),
+            })
+            .expect("a consensus needs at least one report");
+
+        self.agreement.push(share(
+            votes
+                .iter()
+                .map(|(name, _, w)| (*w, if name == winner { 1.0 } else { 0.0 })),
+        ));
+        votes
+            .iter()
+            .find(|(name, _, _)| name == winner)
+            .map(|(_, value, _)| (*value).clone())
+            .expect("the winner is one of the votes")
+    }
+
+    pub fn outliers(&self) -> &[FieldOutlier] {
+        &self.outliers
+    }
+
+    ///0 to 1, how much the reports agreed over every field. A single report can not agree with
+    ///anything so it only gets half, every extra report halves what is left.
+    pub fn confidence(&self) -> f64 {
+        let agreement = match self.agreement.len() {
+            0 => 1.0,
+            n => self.agreement.iter().sum::<f64>() / n as f64,
+        };
+        agreement * (1.0 - 0.5f64.powi(self.data.len() as i32))
+    }
+}
+
+///The value half the weight is at or below
+fn weighted_median(values: &[(f64, f64)]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let half = sorted.iter().map(|(_, w)| w).sum::<f64>() / 2.0;
+    let mut seen = 0.0;
+    for (value, weight) in &sorted {
+        seen += weight;
+        if seen >= half {
+            return *value;
+        }
+    }
+    sorted.last().map(|(v, _)| *v).unwrap_or(0.0)
+}
+
+///The weighted mean of the scores given as (weight, score)
+fn share(scores: impl Iterator<Item = (f64, f64)>) -> f64 {
+    let (total, weight) = scores.fold((0.0, 0.0), |(total, weight), (w, score)| {
+        (total + w * score, weight + w)
+    });
+    if weight > 0.0 {
+        total / weight
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::Complevel, *};
+
+    #[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+    enum Endgame {
+        Climb,
+        Park,
+    }
+
+    #[test]
+    fn numeric_stats() {
+        let stats = NumericStats::new(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(stats.mean, 2.5);
+        assert_eq!(stats.median, 2.5);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.max, 4.0);
+        assert!((stats.std_dev - 1.25f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(NumericStats::new(&[5.0, 1.0, 3.0]).median, 3.0);
+    }
+
+    #[test]
+    fn numeric_stats_of_nothing_is_zero() {
+        let stats = NumericStats::new(&[]);
+        assert_eq!(stats.mean, 0.0);
+        assert_eq!(stats.max, 0.0);
+    }
+
+    #[test]
+    fn enum_stats() {
+        let values = [Endgame::Park, Endgame::Climb, Endgame::Climb];
+        let stats = EnumStats::new(values.iter());
+        assert_eq!(stats.mode, Some(Endgame::Climb));
+        assert_eq!(stats.distribution["Climb"], 2);
+        assert_eq!(stats.distribution["Park"], 1);
+    }
+
+    #[test]
+    fn enum_stats_ties_go_to_the_first() {
+        let values = [Endgame::Park, Endgame::Climb];
+        assert_eq!(EnumStats::new(values.iter()).mode, Some(Endgame::Park));
+    }
+
+    #[test]
+    fn trend_slope() {
+        let points = [1.0, 3.0, 5.0]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| TrendPoint {
+                match_number: MatchNumber {
+                    level: Complevel::Qualifier,
+                    number: i as u32 + 1,
+                },
+                value: *value,
+            })
+            .collect();
+        assert_eq!(FieldTrend::new(points).slope, 2.0);
+    }
+
+    #[test]
+    fn majority_unanimous_has_no_conflict() {
+        let data = [Endgame::Climb, Endgame::Climb];
+        let mut conflicts = Vec::new();
+        let winner = majority(&data, "endgame", |d| d, &mut conflicts);
+        assert_eq!(winner, Some(Endgame::Climb));
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn majority_flags_a_split() {
+        let data = [Endgame::Park, Endgame::Climb, Endgame::Climb];
+        let mut conflicts = Vec::new();
+        let winner = majority(&data, "endgame", |d| d, &mut conflicts);
+        assert_eq!(winner, Some(Endgame::Climb));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field, "endgame");
+        assert_eq!(conflicts[0].votes["Park"], 1);
+    }
+
+    #[test]
+    fn majority_of_nothing() {
+        let data: [Endgame; 0] = [];
+        assert_eq!(majority(&data, "endgame", |d| d, &mut Vec::new()), None);
+    }
+
+    #[test]
+    fn consensus_count_averages_by_weight() {
+        let data = [10, 0];
+        let mut consensus = Consensus::new(&data, &[0.9, 0.1]);
+        assert_eq!(consensus.count("notes", |d| *d), 9);
+        assert!(consensus.outliers().is_empty());
+    }
+
+    #[test]
+    fn consensus_leaves_out_outliers() {
+        let data = [5, 5, 5, 20];
+        let mut consensus = Consensus::new(&data, &[1.0; 4]);
+        assert_eq!(consensus.count("notes", |d| *d), 5);
+
+        let outliers = consensus.outliers();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].report, 3);
+        assert_eq!(outliers[0].field, "notes");
+        assert_eq!(outliers[0].value, 20.0);
+        assert_eq!(outliers[0].consensus, 5.0);
+    }
+
+    #[test]
+    fn consensus_two_reports_are_never_outliers() {
+        let data = [1, 10];
+        let mut consensus = Consensus::new(&data, &[1.0, 1.0]);
+        assert_eq!(consensus.count("notes", |d| *d), 6);
+        assert!(consensus.outliers().is_empty());
+    }
+
+    #[test]
+    fn consensus_off_by_one_is_not_an_outlier() {
+        let data = [3, 3, 4];
+        let mut consensus = Consensus::new(&data, &[1.0; 3]);
+        assert_eq!(consensus.count("notes", |d| *d), 3);
+        assert!(consensus.outliers().is_empty());
+    }
+
+    #[test]
+    fn consensus_vote_goes_to_the_most_weight() {
+        let data = [Endgame::Park, Endgame::Climb, Endgame::Climb];
+        let mut consensus = Consensus::new(&data, &[1.0, 0.1, 0.1]);
+        assert_eq!(consensus.vote(|d| d), Endgame::Park);
+    }
+
+    #[test]
+    fn consensus_confidence() {
+        let one = [Endgame::Climb];
+        let mut consensus = Consensus::new(&one, &[1.0]);
+        consensus.vote(|d| d);
+        assert_eq!(consensus.confidence(), 0.5);
+
+        let agreed = [Endgame::Climb, Endgame::Climb, Endgame::Climb];
+        let mut consensus = Consensus::new(&agreed, &[1.0; 3]);
+        consensus.vote(|d| d);
+        assert_eq!(consensus.confidence(), 0.875);
+
+        let split = [Endgame::Climb, Endgame::Park, Endgame::Climb];
+        let mut consensus = Consensus::new(&split, &[1.0; 3]);
+        consensus.vote(|d| d);
+        assert!(consensus.confidence() < 0.875 * 0.7);
+    }
+}
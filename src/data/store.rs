@@ -0,0 +1,378 @@
+//! The storage interface used by the DataManager.
+//! Every database backend implements [`ScoutStore`] so the rest of the server does not care where
+//! the reports actually end up.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::*;
+use async_trait::async_trait;
+
+use super::{
+    keys::ApiKey,
+    memory::MemoryStore,
+    openscout::{MongoAuth, OpenScoutDB},
+    season::Season,
+    sqlite::SqliteStore,
+    MatchNumber, PitConsensus, TeamMatchReport, TeamMatchReportAvg, TeamPitReport, Visibility,
+};
+
+///The backend picked in the config. One database holds every season, each season gets its own
+///[`ScoutStore`] view of it with [`Storage::for_season`].
+#[derive(Clone)]
+pub enum Storage {
+    Sqlite(Arc<SqliteStore>),
+    Memory(Arc<MemoryStore>),
+    Mongo(Arc<OpenScoutDB>),
+}
+
+impl Storage {
+    ///Picks the backend from the storage url in the config.
+    ///`sqlite://...` opens a sqlite file, `memory://<snapshot file>` keeps everything in ram and
+    ///anything else (or nothing) is treated as a mongo url.
+    pub async fn connect(
+        url: Option<String>,
+        mongo_auth: Option<MongoAuth>,
+        snapshot_interval: Duration,
+    ) -> Result<Self> {
+        match url {
+            Some(url) if url.starts_with("sqlite:") => {
+                Ok(Self::Sqlite(Arc::new(SqliteStore::new(&url).await?)))
+            }
+            Some(url) if url.starts_with("memory://") => {
+                //a bare memory:// never touches the disk
+                let path = Some(url.trim_start_matches("memory://"))
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from);
+                Ok(Self::Memory(Arc::new(MemoryStore::new(
+                    path,
+                    snapshot_interval,
+                )?)))
+            }
+            url => Ok(Self::Mongo(Arc::new(
+                OpenScoutDB::new(url, mongo_auth).await?,
+            ))),
+        }
+    }
+
+    pub fn for_season<S: Season>(&self) -> Arc<dyn ScoutStore<S>> {
+        match self {
+            Self::Sqlite(store) => store.clone(),
+            Self::Memory(store) => store.clone(),
+            Self::Mongo(store) => store.clone(),
+        }
+    }
+
+    ///Makes sure everything is written out, only matters for backends that buffer writes
+    pub fn flush(&self) -> Result<()> {
+        match self {
+            Self::Memory(store) => store.write_snapshot(),
+            _ => Ok(()),
+        }
+    }
+}
+
+///Whose reports a lookup can see
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Viewer {
+    ///admins and servers without auth
+    All,
+    Team(u32),
+}
+
+impl Viewer {
+    pub fn can_see(&self, recording_team: u32, visibility: &Visibility) -> bool {
+        match (self, visibility) {
+            (Viewer::All, _) | (_, Visibility::Public) => true,
+            (Viewer::Team(team), Visibility::Private) => *team == recording_team,
+            (Viewer::Team(team), Visibility::Shared(partners)) => {
+                *team == recording_team || partners.contains(team)
+            }
+        }
+    }
+
+    pub fn owns(&self, recording_team: u32) -> bool {
+        match self {
+            Viewer::All => true,
+            Viewer::Team(team) => *team == recording_team,
+        }
+    }
+}
+
+///Reports that are not always for everyone's eyes. Every backend runs what it found through
+///`for_viewer` even when the query already filtered, so nothing slips past a backend's filter.
+pub trait Shared: Sized {
+    fn recording_team(&self) -> u32;
+
+    fn visibility(&self) -> &Visibility;
+
+    ///Blanks what only the recording team gets to see
+    fn redact(self) -> Self {
+        self
+    }
+
+    ///None when the viewer can not see the report at all
+    fn for_viewer(self, viewer: Viewer) -> Option<Self> {
+        if !viewer.can_see(self.recording_team(), self.visibility()) {
+            return None;
+        }
+        match viewer.owns(self.recording_team()) {
+            true => Some(self),
+            false => Some(self.redact()),
+        }
+    }
+}
+
+///Everything the server needs from a database.
+///Methods that look something up return `StatusCode::NO_CONTENT` as the error when nothing
+///matches, the same as the mongo backend always has.
+///Reports are only ever read back for the season they were written in, the api keys are shared
+///by every season. Every report lookup only gives what the `viewer` is allowed to see.
+#[async_trait]
+pub trait ScoutStore<S: Season>: Send + Sync {
+    async fn post_team_match_data(&self, data: TeamMatchReport<S>) -> Result<()>;
+
+    async fn post_team_pit_data(&self, data: TeamPitReport<S>) -> Result<()>;
+
+    ///Gives the newest report for the team in the given match
+    async fn get_last_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>>;
+
+    async fn get_all_team_match_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>>;
+
+    async fn get_team_match_data_by_induvidual(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        recording_induvidual: String,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>>;
+
+    async fn get_last_team_match_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TeamMatchReport<S>>;
+
+    async fn get_all_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>>;
+
+    ///Every report for the team at the event regardless of the match
+    async fn get_all_team_event_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamMatchReport<S>>>;
+
+    ///Gives the newest pit report for the team
+    async fn get_last_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>>;
+
+    async fn get_all_team_pit_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        event: String,
+    ) -> Result<Vec<TeamPitReport<S>>>;
+
+    async fn get_team_pit_data_by_induvidual(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        recording_induvidual: String,
+        event: String,
+    ) -> Result<TeamPitReport<S>>;
+
+    async fn get_last_team_pit_data_by_team(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        recording_team: u32,
+        event: String,
+    ) -> Result<TeamPitReport<S>>;
+
+    async fn get_all_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<Vec<TeamPitReport<S>>>;
+
+    ///`StatusCode::UNAUTHORIZED` when there is no key with the id
+    async fn get_key(&self, id: &str) -> Result<ApiKey>;
+
+    ///Revoked and expired keys too
+    async fn get_team_keys(&self, team: u32) -> Result<Vec<ApiKey>>;
+
+    ///Every team's keys, revoked ones too
+    async fn get_all_keys(&self) -> Result<Vec<ApiKey>>;
+
+    ///Adds the key or replaces the one with the same id
+    async fn put_key(&self, key: ApiKey) -> Result<()>;
+
+    ///`StatusCode::NOT_FOUND` when there is no key with the id
+    async fn delete_key(&self, id: &str) -> Result<()>;
+
+    //the averages are built on top of the other queries so backends get them for free
+    ///Without a match number every report for the team at the event is used
+    async fn get_avg_team_match_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        match_number: Option<MatchNumber>,
+        event: String,
+    ) -> Result<TeamMatchReportAvg<S>> {
+        let data = match match_number {
+            Some(match_number) => {
+                self.get_all_team_match_data(viewer, team_number, match_number, event)
+                    .await?
+            }
+            None => {
+                self.get_all_team_event_match_data(viewer, team_number, event)
+                    .await?
+            }
+        };
+
+        TeamMatchReport::avg(data)
+    }
+
+    async fn get_avg_team_pit_data(
+        &self,
+        viewer: Viewer,
+        team_number: u32,
+        event: String,
+    ) -> Result<PitConsensus<S>> {
+        TeamPitReport::consensus(
+            self.get_all_team_pit_data(viewer, team_number, event)
+                .await?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{
+        super::season::reefscape::{test_report, BargeEndgame, Reefscape},
+        *,
+    };
+
+    ///A report by team 254 with notes and team specific data in it
+    fn report(visibility: Visibility, private_notes: bool) -> TeamMatchReport<Reefscape> {
+        let mut report = test_report(254, "alice");
+        report.notes = "slow intake".to_string();
+        report.team_spesific_data = Some(HashMap::from([("driver".to_string(), json!("nervous"))]));
+        report.visibility = visibility;
+        report.private_notes = private_notes;
+        report
+    }
+
+    #[test]
+    fn reports_from_before_visibility_are_public() {
+        let mut old = serde_json::to_value(report(Visibility::Private, true)).unwrap();
+        old.as_object_mut().unwrap().remove("visibility");
+        old.as_object_mut().unwrap().remove("private_notes");
+        let old: TeamMatchReport<Reefscape> = serde_json::from_value(old).unwrap();
+        assert_eq!(old.visibility, Visibility::Public);
+        assert!(!old.private_notes);
+    }
+
+    #[test]
+    fn public_is_seen_by_everyone() {
+        let seen = report(Visibility::Public, false)
+            .for_viewer(Viewer::Team(1))
+            .unwrap();
+        assert_eq!(seen.notes, "slow intake");
+        assert!(seen.team_spesific_data.is_some());
+    }
+
+    #[test]
+    fn private_is_only_seen_by_its_team() {
+        assert!(report(Visibility::Private, false)
+            .for_viewer(Viewer::Team(1))
+            .is_none());
+        assert!(report(Visibility::Private, false)
+            .for_viewer(Viewer::Team(254))
+            .is_some());
+        assert!(report(Visibility::Private, false)
+            .for_viewer(Viewer::All)
+            .is_some());
+    }
+
+    #[test]
+    fn shared_is_seen_by_the_teams_listed() {
+        let shared = Visibility::Shared(vec![1, 2]);
+        assert!(report(shared.clone(), false)
+            .for_viewer(Viewer::Team(2))
+            .is_some());
+        assert!(report(shared.clone(), false)
+            .for_viewer(Viewer::Team(3))
+            .is_none());
+        assert!(report(shared, false)
+            .for_viewer(Viewer::Team(254))
+            .is_some());
+    }
+
+    #[test]
+    fn private_notes_are_blanked_for_everyone_else() {
+        let shared = Visibility::Shared(vec![1]);
+        let partner = report(shared.clone(), true)
+            .for_viewer(Viewer::Team(1))
+            .unwrap();
+        assert_eq!(partner.notes, "");
+        assert!(partner.team_spesific_data.is_none());
+        //the data itself is what is being pooled
+        assert!(matches!(partner.data.endgame, BargeEndgame::Park));
+
+        let own = report(shared.clone(), true)
+            .for_viewer(Viewer::Team(254))
+            .unwrap();
+        assert_eq!(own.notes, "slow intake");
+        assert!(own.team_spesific_data.is_some());
+
+        let admin = report(shared, true).for_viewer(Viewer::All).unwrap();
+        assert_eq!(admin.notes, "slow intake");
+    }
+
+    #[test]
+    fn viewer_can_see() {
+        let shared = Visibility::Shared(vec![1]);
+        assert!(Viewer::All.can_see(254, &Visibility::Private));
+        assert!(Viewer::Team(1).can_see(254, &Visibility::Public));
+        assert!(Viewer::Team(1).can_see(254, &shared));
+        assert!(!Viewer::Team(2).can_see(254, &shared));
+        assert!(!Viewer::Team(1).can_see(254, &Visibility::Private));
+        assert!(Viewer::Team(254).can_see(254, &Visibility::Private));
+    }
+}
@@ -1,29 +1,41 @@
 use anyhow::*;
+use async_trait::async_trait;
 use core::time;
 use log::info;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, iter::zip};
-use utoipa::ToSchema;
+use std::{collections::HashMap, iter::zip, time::Duration};
 
 use crate::data::Complevel;
 
-use super::{Allience, Eventdata, MatchNumber};
+use super::{
+    cache::HttpCache,
+    season::Season,
+    source::{EventSource, Ranking},
+    Allience, Eventdata, MatchNumber,
+};
+
+//how long each kind of response is good for, results change every few minutes during an event
+//while the team and event lists barely change at all
+const MATCH_TTL: Duration = Duration::from_secs(30);
+const RANKINGS_TTL: Duration = Duration::from_secs(60);
+const OPR_TTL: Duration = Duration::from_secs(5 * 60);
+const TEAM_LIST_TTL: Duration = Duration::from_secs(60 * 60);
+const EVENT_LIST_TTL: Duration = Duration::from_secs(24 * 60 * 60);
 
 #[derive(Clone)]
 pub struct TheBlueAllience {
     client: reqwest::Client,
+    cache: HttpCache,
     key: String,
 }
 
 impl TheBlueAllience {
-    pub async fn new(key: String) -> Result<Self> {
-        let tba = Self {
+    pub fn new(key: String, cache: HttpCache) -> Self {
+        Self {
             client: reqwest::Client::new(),
+            cache,
             key,
-        };
-        tba.check().await?;
-
-        Ok(tba)
+        }
     }
 
     ///Checks if the TBA api is working
@@ -40,21 +52,24 @@ impl TheBlueAllience {
         Ok(())
     }
 
+    ///The opr table for every team at the event
+    pub async fn get_oprs(&self, event: String) -> Result<Oprs> {
+        self.cache
+            .get_json::<Oprs>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/event/{}/oprs",
+                        event
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                OPR_TTL,
+            )
+            .await
+    }
+
     pub async fn get_team_data(&self, team_num: u32, event: String) -> Result<TbaTeamdata> {
         info!("requesting opr data from tba");
-        let opr_request = self
-            .client
-            .get(format!(
-                "https://www.thebluealliance.com/api/v3/event/{}/oprs",
-                event
-            ))
-            .header("X-TBA-Auth-Key", &self.key)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<Oprs>()
-            .await?;
-
+        let opr_request = self.get_oprs(event).await?;
         info!("recived opr data from tba");
 
         Ok(TbaTeamdata {
@@ -77,24 +92,24 @@ impl TheBlueAllience {
         })
     }
 
-    pub async fn get_match_data(
+    pub async fn get_match_data<S: Season>(
         &self,
         match_number: MatchNumber,
         event: String,
-    ) -> Result<TbaMatchData> {
+    ) -> Result<TbaMatchData<S>> {
         let match_key = format!("{}_{}", event, match_number.get_tba_string()?);
 
         let match_request = self
-            .client
-            .get(format!(
-                "https://www.thebluealliance.com/api/v3/match/{}",
-                match_key
-            ))
-            .header("X-TBA-Auth-Key", &self.key)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<TbaSerdeMatchBreakDown>()
+            .cache
+            .get_json::<TbaSerdeMatchBreakDown<S::ScoreBreakdown>>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/match/{}",
+                        match_key
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                MATCH_TTL,
+            )
             .await?;
 
         Ok(TbaMatchData {
@@ -108,35 +123,44 @@ impl TheBlueAllience {
             blue_allience: match_request.alliances.blue.get_team_nums(),
             red_score: match_request.alliances.red.score,
             blue_score: match_request.alliances.blue.score,
-            red_score_breakdown: match_request.score_breakdown.red,
-            blue_score_breakdown: match_request.score_breakdown.blue,
+            red_score_breakdown: match_request
+                .score_breakdown
+                .as_ref()
+                .map(|b| b.red.clone()),
+            blue_score_breakdown: match_request.score_breakdown.map(|b| b.blue),
             time: match_request.time,
             predicted_time: match_request.predicted_time,
             actual_time: match_request.actual_time,
         })
     }
 
-    pub async fn get_match_data_list(&self, event: String) -> Result<Vec<TbaMatchData>> {
+    pub async fn get_match_data_list<S: Season>(
+        &self,
+        event: String,
+    ) -> Result<Vec<TbaMatchData<S>>> {
         let matches_request = self
-            .client
-            .get(format!(
-                "https://www.thebluealliance.com/api/v3/{}/matches/",
-                event
-            ))
-            .header("X-TBA-Auth-Key", &self.key)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<Vec<TbaSerdeMatchBreakDown>>()
+            .cache
+            .get_json::<Vec<TbaSerdeMatchBreakDown<S::ScoreBreakdown>>>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/event/{}/matches",
+                        event
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                MATCH_TTL,
+            )
             .await?;
 
         let mut result = Vec::new();
 
         for i in matches_request {
             let match_number = MatchNumber {
-                number: i.match_number,
+                //playoffs are one match per set so the set is the match number there
+                number: match i.comp_level.as_str() {
+                    "sf" => i.set_number,
+                    _ => i.match_number,
+                },
 
-                //TODO: I don't know if this method of getting the match number is correct
                 level: match i.comp_level.as_str() {
                     "p" => crate::data::Complevel::Practice,
                     "qm" => Complevel::Qualifier,
@@ -157,8 +181,8 @@ impl TheBlueAllience {
                 blue_allience: i.alliances.blue.get_team_nums(),
                 red_score: i.alliances.red.score,
                 blue_score: i.alliances.blue.score,
-                red_score_breakdown: i.score_breakdown.red,
-                blue_score_breakdown: i.score_breakdown.blue,
+                red_score_breakdown: i.score_breakdown.as_ref().map(|b| b.red.clone()),
+                blue_score_breakdown: i.score_breakdown.map(|b| b.blue),
                 time: i.time,
                 predicted_time: i.predicted_time,
                 actual_time: i.actual_time,
@@ -168,49 +192,122 @@ impl TheBlueAllience {
         Ok(result)
     }
 
-    pub async fn get_event_list(&self) -> Result<Vec<Eventdata>> {
+    pub async fn get_rankings(&self, event: String) -> Result<Vec<Ranking>> {
+        let rankings_request = self
+            .cache
+            .get_json::<TbaSerdeRankings>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/event/{}/rankings",
+                        event
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                RANKINGS_TTL,
+            )
+            .await?;
+
+        Ok(rankings_request
+            .rankings
+            .into_iter()
+            .map(|r| Ranking {
+                rank: r.rank,
+                team_number: team_key_number(&r.team_key),
+                wins: r.record.wins,
+                losses: r.record.losses,
+                ties: r.record.ties,
+                matches_played: r.matches_played,
+                ranking_score: r.sort_orders.first().copied().unwrap_or(0.0),
+            })
+            .collect())
+    }
+
+    pub async fn get_team_list(&self, event: String) -> Result<Vec<u32>> {
+        let teams_request = self
+            .cache
+            .get_json::<Vec<String>>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/event/{}/teams/keys",
+                        event
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                TEAM_LIST_TTL,
+            )
+            .await?;
+
+        Ok(teams_request
+            .iter()
+            .map(|key| team_key_number(key))
+            .collect())
+    }
+
+    pub async fn get_event_list(&self, year: u32) -> Result<Vec<Eventdata>> {
         let event_request = self
-            .client
-            .get(format!(
-                "https://www.thebluealliance.com/api/v3/events/{}",
-                env!("CARGO_PKG_VERSION")
-                    .split(".")
-                    .into_iter()
-                    .next()
-                    .expect("This error will never trigger since the first number if the version will always be the year")
-            ))
-            .header("X-TBA-Auth-Key", &self.key)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<Vec<Eventdata>>()
+            .cache
+            .get_json::<Vec<Eventdata>>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/events/{}",
+                        year
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                EVENT_LIST_TTL,
+            )
             .await?;
 
         Ok(event_request)
     }
 
-    pub async fn get_event_keys(&self) -> Result<Vec<String>> {
+    pub async fn get_event_keys(&self, year: u32) -> Result<Vec<String>> {
         let event_request = self
-            .client
-            .get(format!(
-                "https://www.thebluealliance.com/api/v3/events/{}/keys",
-                env!("CARGO_PKG_VERSION")
-                    .split(".")
-                    .into_iter()
-                    .next()
-                    .expect("This error will never trigger since the first number if the version will always be the year")
-            ))
-            .header("X-TBA-Auth-Key", &self.key)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<Vec<String>>()
+            .cache
+            .get_json::<Vec<String>>(
+                self.client
+                    .get(format!(
+                        "https://www.thebluealliance.com/api/v3/events/{}/keys",
+                        year
+                    ))
+                    .header("X-TBA-Auth-Key", &self.key),
+                EVENT_LIST_TTL,
+            )
             .await?;
 
         Ok(event_request)
     }
 }
 
+#[async_trait]
+impl<S: Season> EventSource<S> for TheBlueAllience {
+    fn name(&self) -> &'static str {
+        "tba"
+    }
+
+    async fn get_match_data(
+        &self,
+        match_number: MatchNumber,
+        event: String,
+    ) -> Result<TbaMatchData<S>> {
+        TheBlueAllience::get_match_data(self, match_number, event).await
+    }
+
+    async fn get_match_data_list(&self, event: String) -> Result<Vec<TbaMatchData<S>>> {
+        TheBlueAllience::get_match_data_list(self, event).await
+    }
+
+    async fn get_rankings(&self, event: String) -> Result<Vec<Ranking>> {
+        TheBlueAllience::get_rankings(self, event).await
+    }
+
+    async fn get_team_list(&self, event: String) -> Result<Vec<u32>> {
+        TheBlueAllience::get_team_list(self, event).await
+    }
+}
+
+///`frc254` to 254
+fn team_key_number(key: &str) -> u32 {
+    key.trim_start_matches("frc").parse().unwrap_or(0)
+}
+
 pub struct TbaTeamdata {
     pub team_num: u32,
     pub opr: f64,
@@ -218,22 +315,22 @@ pub struct TbaTeamdata {
     pub ccwm: f64,
 }
 
-pub struct TbaMatchData {
+pub struct TbaMatchData<S: Season> {
     pub match_number: MatchNumber,
     pub winning_allience: Option<Allience>,
     pub red_allience: [u32; 3],
     pub blue_allience: [u32; 3],
     pub red_score: u32,
     pub blue_score: u32,
-    pub red_score_breakdown: TbaScoreBreakdown,
-    pub blue_score_breakdown: TbaScoreBreakdown,
-    pub time: u64,
-    pub actual_time: u64,
-    pub predicted_time: u64,
+    pub red_score_breakdown: Option<S::ScoreBreakdown>,
+    pub blue_score_breakdown: Option<S::ScoreBreakdown>,
+    pub time: Option<u64>,
+    pub actual_time: Option<u64>,
+    pub predicted_time: Option<u64>,
 }
 #[allow(nonstandard_style)]
 #[derive(Debug, Serialize, Deserialize)]
-struct Oprs {
+pub struct Oprs {
     oprs: HashMap<String, f64>,
     dprs: HashMap<String, f64>,
     ccwms: HashMap<String, f64>,
@@ -241,16 +338,40 @@ struct Oprs {
 #[allow(nonstandard_style)]
 ///A intermidiary struct to
 #[derive(Debug, Serialize, Deserialize)]
-struct TbaSerdeMatchBreakDown {
+struct TbaSerdeMatchBreakDown<B> {
     match_number: u32,
+    set_number: u32,
     comp_level: String,
     alliances: TbaSerdeAlliences,
     winning_alliance: String,
-    score_breakdown: TbaSerdeScoreBreakdowns,
-    time: u64,
-    actual_time: u64,
-    predicted_time: u64,
+    //everything below is null until the match is played (or scheduled for the times)
+    score_breakdown: Option<TbaSerdeScoreBreakdowns<B>>,
+    time: Option<u64>,
+    actual_time: Option<u64>,
+    predicted_time: Option<u64>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+struct TbaSerdeRankings {
+    rankings: Vec<TbaSerdeRanking>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TbaSerdeRanking {
+    rank: u32,
+    team_key: String,
+    record: TbaSerdeRecord,
+    matches_played: u32,
+    #[serde(default)]
+    sort_orders: Vec<f64>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TbaSerdeRecord {
+    wins: u32,
+    losses: u32,
+    ties: u32,
+}
+
 #[allow(nonstandard_style)]
 #[derive(Debug, Serialize, Deserialize)]
 struct TbaSerdeAlliences {
@@ -285,17 +406,7 @@ impl TbaSerdeAllience {
 }
 #[allow(nonstandard_style)]
 #[derive(Debug, Serialize, Deserialize)]
-struct TbaSerdeScoreBreakdowns {
-    red: TbaScoreBreakdown,
-    blue: TbaScoreBreakdown,
-}
-
-#[allow(nonstandard_style)]
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
-pub struct TbaScoreBreakdown {
-    autoPoints: u32,
-    teleopPoints: u32,
-    adjustPoints: u32,
-    foulPoints: u32,
-    //this is not every points field provided by the tba api but they seem to be the most useful
+struct TbaSerdeScoreBreakdowns<B> {
+    red: B,
+    blue: B,
 }
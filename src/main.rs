@@ -1,32 +1,64 @@
 use std::{
+    any::Any,
     fs::{self, File},
     io::BufReader,
     path::PathBuf,
+    time::Duration,
 };
 
+use assignments::{MatchCoverage, ScoutingAssignment, ShiftRequest, ShiftSchedule};
 use axum::{
-    extract::{self, Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{self, Path, Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use clap::{Parser, Subcommand};
 use data::{
-    openscout::{Auth, AuthLevel, MongoAuth},
-    Complevel, DataManager, Eventdata, MatchData, MatchNumber, TeamData, TeamMatchReport,
-    TeamPitReport,
+    accuracy::ScoutingAccuracy,
+    frcevents::FrcApiAuth,
+    keys::{IssuedKey, KeyInfo, KeyRequest, KeyUpdate, TeamAccount},
+    openscout::{Auth, MongoAuth},
+    pack::EventPack,
+    season::{CurrentSeason, Season},
+    session::{
+        Authorized, ChangeOwnKey, LoginRequest, ManageKeys, ManageScouts, ManageServer,
+        PostReports, ReadData, ScoutMatches, Session,
+    },
+    source::Ranking,
+    store::Storage,
+    Complevel, CurrentMatch, DataManager, DataManagerOptions, Eventdata, MatchConsensus, MatchData,
+    MatchNumber, PitConsensus, ReportCheck, TeamMatchReport, TeamMatchReportAvg, TeamPitReport,
+    TeamProfile, TeamSeason,
 };
 use log::error;
 use serde::{Deserialize, Serialize};
 use simplelog::Config;
-use utoipa::OpenApi;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
 use utoipa_axum::{router::OpenApiRouter, routes};
 use utoipa_swagger_ui::SwaggerUi;
 
 mod assignments;
 mod data;
 
+///Makes the routes for a season, see season_api.rs
+macro_rules! season_api {
+    ($module:ident, $season:ty) => {
+        mod $module {
+            use super::*;
+            type S = $season;
+            include!("season_api.rs");
+        }
+    };
+}
+
+season_api!(crescendo, data::season::crescendo::Crescendo);
+season_api!(reefscape, data::season::reefscape::Reefscape);
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
@@ -40,19 +72,59 @@ struct Args {
 #[derive(Debug, Subcommand)]
 enum SubCommand {
     version,
+    ///Downloads everything the server needs for the events into an event pack so it can run
+    ///without internet, point `event_pack` in the config at the file to use it
+    Pack {
+        ///TBA event keys, eg. 2024casj
+        #[arg(required = true)]
+        events: Vec<String>,
+        #[arg(short, long, default_value = "eventpack.json")]
+        output: PathBuf,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct OSConfig {
     tba_key: String,
     enable_auth: Option<bool>,
+    ///the minimum number of scouts the global assignment pool puts on every robot, defaults to 1
+    scouts_per_robot: Option<u32>,
+    ///minutes after a match starts that it is considered over for current match detection,
+    ///defaults to 10
+    dead_match_minutes: Option<u64>,
+    ///username and token for the FRC Events API, it is only used when this is set
+    frc_api: Option<FrcApiAuth>,
+    ///which schedule and result sources to ask and in what order, `frc` and/or `tba`.
+    ///defaults to `["frc", "tba"]`
+    source_order: Option<Vec<String>>,
+    ///an event pack made with the pack subcommand, TBA and Statbotics data comes only from it
+    ///when this is set
+    event_pack: Option<PathBuf>,
+    ///where reports are stored, eg. `sqlite:///var/lib/openscout.db`,
+    ///`memory:///var/lib/openscout.json` or a mongodb url. mongo_url is used when this is not set
+    storage: Option<String>,
+    ///seconds between snapshots of the memory storage, defaults to 30
+    snapshot_interval: Option<u64>,
     mongo_url: Option<String>,
+    ///older seasons to serve next to the current one, eg. `[2024]`. Each season is under
+    ///`/{year}/...`, the current one is also at the root like before
+    seasons: Option<Vec<u32>>,
 
     mongo_auth: Option<MongoAuth>,
     admin_auth: Option<Auth>,
+    ///signs the bearer tokens from /login, without it the tokens only last until a restart
+    token_secret: Option<String>,
+    ///how long a token from /login lasts, defaults to 12 (an event day)
+    session_hours: Option<u64>,
+}
+
+fn load_config(path: &PathBuf) -> OSConfig {
+    toml::from_str(fs::read_to_string(path).expect("can't load args").as_str())
+        .expect("Can't parse config file")
 }
 
 #[derive(OpenApi)]
+#[openapi(modifiers(&BearerAuth), security(("bearer" = [])))]
 //#[openapi(
 //    tags(
 //        (name = CUSTOMER_TAG, description = "Customer API endpoints"),
@@ -61,6 +133,26 @@ struct OSConfig {
 //)]
 struct ApiDoc;
 
+///Lets swagger ui log in with a token from /login
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+    }
+}
+
 #[tokio::main]
 async fn main() -> () {
     let args = Args::parse();
@@ -69,37 +161,74 @@ async fn main() -> () {
         match cmd {
             SubCommand::version => {
                 println!("{}", env!("CARGO_PKG_VERSION"));
-                return;
+            }
+            SubCommand::Pack { events, output } => {
+                let config = load_config(&args.config);
+                EventPack::download::<CurrentSeason>(config.tba_key, events)
+                    .await
+                    .expect("Can't download the event pack")
+                    .save(&output)
+                    .expect("Can't write the event pack");
+                println!("event pack written to {}", output.display());
             }
         }
+        return;
     }
 
-    let config: OSConfig = toml::from_str(
-        fs::read_to_string(args.config)
-            .expect("can't load args")
-            .as_str(),
+    let config = load_config(&args.config);
+
+    let storage = Storage::connect(
+        config.storage.or(config.mongo_url),
+        config.mongo_auth,
+        Duration::from_secs(config.snapshot_interval.unwrap_or(30)),
     )
-    .expect("Can't parse config file");
+    .await
+    .expect("Can't open the database");
 
-    let dm = data::DataManager::new(config.tba_key, config.mongo_auth, config.enable_auth)
-        .await
-        .unwrap();
+    let mut seasons = config.seasons.unwrap_or_default();
+    seasons.push(CurrentSeason::YEAR);
+    seasons.sort();
+    seasons.dedup();
+
+    let dm = data::DataManager::new(
+        DataManagerOptions {
+            tba_key: config.tba_key,
+            enable_auth: config.enable_auth,
+            scouts_per_robot: config.scouts_per_robot,
+            dead_match_minutes: config.dead_match_minutes,
+            frc_api: config.frc_api,
+            source_order: config.source_order,
+            event_pack: config.event_pack,
+            seasons: seasons.clone(),
+            token_secret: config.token_secret,
+            session_hours: config.session_hours,
+        },
+        storage.for_season::<CurrentSeason>(),
+    )
+    .await
+    .unwrap();
 
     if let Some(auth) = config.admin_auth {
-        dm.add_user(auth)
-            .await
-            .unwrap_or(error!("Unable to set admin credentials"));
+        if let Err(e) = dm.add_user(auth).await {
+            error!("Unable to set admin credentials: {}", e);
+        }
     }
 
-    let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
-        //not sure I'm happy with how many time i typed route
-        .routes(routes!(get_match_data))
-        .routes(routes!(get_team_data))
-        .routes(routes!(get_team_pit_data, post_team_pit_data))
-        .routes(routes!(get_team_match_data, post_team_match_data))
+    let mut router = OpenApiRouter::with_openapi(ApiDoc::openapi())
         .routes(routes!(get_server_version))
-        .routes(routes!(get_event_list))
+        .routes(routes!(login))
         .routes(routes!(add_user))
+        .routes(routes!(get_keys, issue_key))
+        .routes(routes!(update_key, delete_key))
+        .routes(routes!(revoke_key))
+        .routes(routes!(rotate_key))
+        .routes(routes!(change_own_key))
+        .routes(routes!(get_teams))
+        .routes(routes!(delete_team))
+        .routes(routes!(disable_team))
+        .routes(routes!(enable_team))
+        .routes(routes!(get_seasons))
+        .routes(routes!(get_team_history))
         //.nest("/api/customer", customer::router())
         //.nest("/api/order", order::router())
         //.routes(routes!(
@@ -107,131 +236,313 @@ async fn main() -> () {
         //    inner::secret_handlers::post_secret
         //)
         //)
-        .split_for_parts();
+        .with_state(dm.clone())
+        //the current season stays at the root so older apps keep working
+        .merge(
+            season_router(CurrentSeason::YEAR, &dm, &storage)
+                .await
+                .unwrap(),
+        );
+    for year in seasons {
+        let season = season_router(year, &dm, &storage)
+            .await
+            .expect("Can't serve the season");
+        router = router.nest(&format!("/{}", year), season);
+    }
+    let (router, mut api) = router.split_for_parts();
+    tag_season_operations(&mut api);
 
-    let app: Router<()> = router
-        .merge(SwaggerUi::new("/swagger-ui").url("/apidoc/openapi.json", api))
-        .with_state(dm);
+    let app: Router<()> =
+        router.merge(SwaggerUi::new("/swagger-ui").url("/apidoc/openapi.json", api));
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Can't listen for ctrl-c");
+        })
+        .await
+        .unwrap();
+
+    //the memory storage would otherwise lose anything since its last snapshot
+    if let Err(e) = storage.flush() {
+        error!("Unable to flush the database: {}", e);
+    }
+}
+
+///The routes for a season by its year. Every season module made with season_api! needs an arm
+///here.
+async fn season_router(
+    year: u32,
+    current: &DataManager<CurrentSeason>,
+    storage: &Storage,
+) -> anyhow::Result<OpenApiRouter> {
+    match year {
+        2024 => hosted(crescendo::router(), current, storage).await,
+        2025 => hosted(reefscape::router(), current, storage).await,
+        year => Err(anyhow::anyhow!(
+            "there is no game for {} in this build",
+            year
+        )),
+    }
+}
+
+//the current season reuses its DataManager so the root and /{year} routes share assignments
+async fn hosted<S: Season>(
+    router: OpenApiRouter<DataManager<S>>,
+    current: &DataManager<CurrentSeason>,
+    storage: &Storage,
+) -> anyhow::Result<OpenApiRouter> {
+    let dm = match (current as &dyn Any).downcast_ref::<DataManager<S>>() {
+        Some(dm) => dm.clone(),
+        None => current.for_season(storage.for_season::<S>()).await?,
+    };
+    Ok(router.with_state(dm))
+}
+
+///Every season has the same handlers so the ones under /{year} get the year added to their
+///operation ids, openapi wants them unique
+fn tag_season_operations(api: &mut utoipa::openapi::OpenApi) {
+    for (path, item) in api.paths.paths.iter_mut() {
+        let Some(year) = path
+            .split('/')
+            .nth(1)
+            .filter(|segment| segment.parse::<u32>().is_ok())
+        else {
+            continue;
+        };
+
+        for operation in [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if let Some(id) = &mut operation.operation_id {
+                *id = format!("{}_{}", id, year);
+            }
+        }
+    }
 }
 
 //this will be the last thing implmented due to how painful it will be to write the query
 //async fn get_event_data() {}
 
-#[utoipa::path(get, path = "/matchdata/{event}/{complevel}/{match_num}", responses((status = 200, body = MatchData)), params(
-        ("event" = String, Path, description = "The event id (blue allience format)"),
-        ("complevel" = Complevel, Path, description = "The level of play"),
-        ("match_num" = u32, Path, description = "the match number")
-    )) ]
-async fn get_match_data(
-    Path(matchd): Path<MatchQuery>,
-    headers: HeaderMap,
-    State(dm): State<DataManager>,
-) -> Result<Json<MatchData>, AppError> {
-    dm.check_auth(&headers, AuthLevel::TEAM).await?;
-    Ok(Json(
-        dm.get_match_data(
-            matchd.event,
-            MatchNumber {
-                number: matchd.match_num,
-                level: matchd.complevel,
-            },
-        )
-        .await?,
-    ))
+#[utoipa::path(post, path = "/login", request_body = LoginRequest, responses((status = OK, body = Session)), security(())) ]
+async fn login(
+    State(dm): State<DataManager<CurrentSeason>>,
+    Json(request): Json<LoginRequest>,
+) -> Result<Json<Session>, AppError> {
+    Ok(Json(dm.login(request).await?))
 }
 
-#[utoipa::path(get, path = "/teamdata/{team_number}/{event}", responses((status = OK, body = data::TeamData)), params(
-    ("team_number" = u32, Path, description = "The team number"),
-    ("event" = String, Path, description = "The event id (blue allience format)")
+#[utoipa::path(post, path = "/adduser", responses(
+    (status = OK),
+    (status = CONFLICT, description = "The team is disabled, enable it first"),
 )) ]
-async fn get_team_data(
-    Path((team_number, event)): Path<(u32, String)>,
-    headers: HeaderMap,
-    State(dm): State<DataManager>,
-) -> Result<Json<data::TeamData>, AppError> {
-    dm.check_auth(&headers, AuthLevel::TEAM).await?;
-    Ok(Json(dm.get_team_data(team_number, event).await?))
-}
-
-#[utoipa::path(post, path = "/teammatchdata", responses((status = OK))) ]
-async fn post_team_match_data(
-    State(dm): State<DataManager>,
-    headers: HeaderMap,
-    extract::Json(data): extract::Json<TeamMatchReport>,
+async fn add_user(
+    State(dm): State<DataManager<CurrentSeason>>,
+    _: Authorized<ManageServer>,
+    Json(auth): Json<Auth>,
 ) -> Result<(), AppError> {
-    dm.check_auth(&headers, AuthLevel::TEAM).await?;
-    dm.post_team_match_data(data).await?;
+    dm.add_user(auth).await?;
     Ok(())
 }
 
-#[utoipa::path(post, path = "/teampitdata", responses((status = OK))) ]
-async fn post_team_pit_data(
-    State(dm): State<DataManager>,
-    headers: HeaderMap,
-    extract::Json(data): extract::Json<TeamPitReport>,
-) -> Result<(), AppError> {
-    dm.check_auth(&headers, AuthLevel::TEAM).await?;
-    dm.post_team_pit_data(data).await?;
-    Ok(())
+#[utoipa::path(get, path = "/keys/{team_num}", responses((status = OK, body = Vec<KeyInfo>)), params(
+    ("team_num" = u32, Path, description = "The team number"),
+)) ]
+async fn get_keys(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ManageKeys>,
+    Path(team_num): Path<u32>,
+) -> Result<Json<Vec<KeyInfo>>, AppError> {
+    dm.check_key_access(auth.caller.as_ref(), team_num)?;
+    Ok(Json(dm.get_keys(team_num).await?))
+}
+
+#[utoipa::path(post, path = "/keys/{team_num}", request_body = KeyRequest, responses((status = OK, body = IssuedKey, description = "The key is only ever shown here")), params(
+    ("team_num" = u32, Path, description = "The team number"),
+)) ]
+async fn issue_key(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ManageKeys>,
+    Path(team_num): Path<u32>,
+    Json(request): Json<KeyRequest>,
+) -> Result<Json<IssuedKey>, AppError> {
+    let limit = dm.check_key_access(auth.caller.as_ref(), team_num)?;
+    Ok(Json(dm.issue_key(team_num, request, limit).await?))
 }
 
-#[utoipa::path(get, path = "/teammatchdata/last/{team_number}/{event}/{complevel}/{match_num}", responses((status = OK, body = TeamMatchReport)), params(
-    ("team_number" = u32, Path, description = "the team number"),
-    ("event" = String, Path, description = "The event id (blue alliance format)"),
-    ("complevel" = Complevel, Path, description = "The level of competition"),
-    ("match_num" = u32, Path, description = "The match number"),
+#[utoipa::path(post, path = "/keys/{team_num}/{key_id}/revoke", responses((status = OK, body = KeyInfo)), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("key_id" = String, Path, description = "The part of the key before the dot"),
 )) ]
-async fn get_team_match_data(
-    Path(matchd): Path<TeamMatchQuery>,
-    headers: HeaderMap,
-    State(dm): State<DataManager>,
-) -> Result<Json<TeamMatchReport>, AppError> {
-    dm.check_auth(&headers, AuthLevel::TEAM).await?;
+async fn revoke_key(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ManageKeys>,
+    Path(path): Path<KeyPath>,
+) -> Result<Json<KeyInfo>, AppError> {
+    let limit = dm.check_key_access(auth.caller.as_ref(), path.team_num)?;
     Ok(Json(
-        dm.get_last_team_match_data(
-            matchd.team_num,
-            MatchNumber {
-                number: matchd.match_num,
-                level: matchd.complevel,
-            },
-            matchd.event,
-        )
-        .await?,
+        dm.revoke_key(path.team_num, path.key_id, limit).await?,
     ))
 }
 
-#[utoipa::path(get, path = "/teampitdata/last/{team_num}/{event}", responses((status = OK, body = TeamPitReport)), params(
+#[utoipa::path(post, path = "/keys/{team_num}/{key_id}/rotate", responses((status = OK, body = IssuedKey, description = "The old key stops working straight away")), params(
     ("team_num" = u32, Path, description = "The team number"),
-    ("event" = String, Path, description = "The event id (blue alliance format)")
+    ("key_id" = String, Path, description = "The part of the key before the dot"),
 )) ]
-async fn get_team_pit_data(
-    State(dm): State<DataManager>,
-    headers: HeaderMap,
-    Path((team_num, event)): Path<(u32, String)>,
-) -> Result<Json<TeamPitReport>, AppError> {
-    dm.check_auth(&headers, AuthLevel::TEAM).await?;
-    Ok(Json(dm.get_last_team_pit_data(team_num, event).await?))
+async fn rotate_key(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ManageKeys>,
+    Path(path): Path<KeyPath>,
+) -> Result<Json<IssuedKey>, AppError> {
+    let limit = dm.check_key_access(auth.caller.as_ref(), path.team_num)?;
+    Ok(Json(
+        dm.rotate_key(path.team_num, path.key_id, limit).await?,
+    ))
 }
 
-async fn get_scouting_assignment() {}
+#[utoipa::path(patch, path = "/keys/{team_num}/{key_id}", request_body = KeyUpdate, responses(
+    (status = OK, body = KeyInfo),
+    (status = FORBIDDEN, description = "Only an admin can touch or hand out ADMIN keys"),
+    (status = NOT_FOUND, description = "The team has no key with that id"),
+), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("key_id" = String, Path, description = "The part of the key before the dot"),
+)) ]
+async fn update_key(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ManageKeys>,
+    Path(path): Path<KeyPath>,
+    Json(update): Json<KeyUpdate>,
+) -> Result<Json<KeyInfo>, AppError> {
+    let limit = dm.check_key_access(auth.caller.as_ref(), path.team_num)?;
+    Ok(Json(
+        dm.update_key(path.team_num, path.key_id, update, limit)
+            .await?,
+    ))
+}
 
-#[axum::debug_handler]
-#[utoipa::path(get, path = "/eventlist", responses((status = OK, body = Vec<Eventdata>))) ]
-async fn get_event_list(State(dm): State<DataManager>) -> Result<Json<Vec<Eventdata>>, AppError> {
-    Ok(Json(dm.get_event_data().await?))
+#[utoipa::path(delete, path = "/keys/{team_num}/{key_id}", responses(
+    (status = NO_CONTENT),
+    (status = FORBIDDEN, description = "Only an admin can delete ADMIN keys"),
+    (status = NOT_FOUND, description = "The team has no key with that id"),
+), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("key_id" = String, Path, description = "The part of the key before the dot"),
+)) ]
+async fn delete_key(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ManageKeys>,
+    Path(path): Path<KeyPath>,
+) -> Result<StatusCode, AppError> {
+    let limit = dm.check_key_access(auth.caller.as_ref(), path.team_num)?;
+    dm.delete_key(path.team_num, path.key_id, limit).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
-#[utoipa::path(post, path = "/adduser", responses((status = OK))) ]
-async fn add_user(
-    State(dm): State<DataManager>,
-    headers: HeaderMap,
-    Json(auth): Json<Auth>,
-) -> Result<(), AppError> {
-    dm.check_auth(&headers, AuthLevel::ADMIN).await?;
-    dm.add_user(auth).await?;
-    Ok(())
+///Any key can change itself, scouts included
+#[utoipa::path(post, path = "/changekey", responses(
+    (status = OK, body = IssuedKey, description = "The old key and its tokens stop working straight away"),
+    (status = BAD_REQUEST, description = "Auth is off so there is no key to change"),
+)) ]
+async fn change_own_key(
+    State(dm): State<DataManager<CurrentSeason>>,
+    auth: Authorized<ChangeOwnKey>,
+) -> Result<Json<IssuedKey>, AppError> {
+    Ok(Json(dm.change_own_key(auth.caller).await?))
+}
+
+#[utoipa::path(get, path = "/teams", responses((status = OK, body = Vec<TeamAccount>))) ]
+async fn get_teams(
+    State(dm): State<DataManager<CurrentSeason>>,
+    _: Authorized<ManageServer>,
+) -> Result<Json<Vec<TeamAccount>>, AppError> {
+    Ok(Json(dm.get_teams().await?))
+}
+
+#[utoipa::path(delete, path = "/teams/{team_num}", responses(
+    (status = NO_CONTENT, description = "Every key the team had is gone, their reports are kept"),
+    (status = NOT_FOUND, description = "The team has no keys"),
+), params(
+    ("team_num" = u32, Path, description = "The team number"),
+)) ]
+async fn delete_team(
+    State(dm): State<DataManager<CurrentSeason>>,
+    _: Authorized<ManageServer>,
+    Path(team_num): Path<u32>,
+) -> Result<StatusCode, AppError> {
+    dm.delete_team(team_num).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(post, path = "/teams/{team_num}/disable", responses(
+    (status = OK, body = TeamAccount),
+    (status = NOT_FOUND, description = "The team has no keys"),
+), params(
+    ("team_num" = u32, Path, description = "The team number"),
+)) ]
+async fn disable_team(
+    State(dm): State<DataManager<CurrentSeason>>,
+    _: Authorized<ManageServer>,
+    Path(team_num): Path<u32>,
+) -> Result<Json<TeamAccount>, AppError> {
+    Ok(Json(dm.set_team_disabled(team_num, true).await?))
+}
+
+#[utoipa::path(post, path = "/teams/{team_num}/enable", responses(
+    (status = OK, body = TeamAccount),
+    (status = NOT_FOUND, description = "The team has no keys"),
+), params(
+    ("team_num" = u32, Path, description = "The team number"),
+)) ]
+async fn enable_team(
+    State(dm): State<DataManager<CurrentSeason>>,
+    _: Authorized<ManageServer>,
+    Path(team_num): Path<u32>,
+) -> Result<Json<TeamAccount>, AppError> {
+    Ok(Json(dm.set_team_disabled(team_num, false).await?))
+}
+
+#[utoipa::path(get, path = "/seasons", responses((status = OK, body = Vec<u32>, description = "The years of every season the server hosts, each one is under /{year}")), security(())) ]
+async fn get_seasons(State(dm): State<DataManager<CurrentSeason>>) -> Json<Vec<u32>> {
+    Json(dm.get_seasons())
+}
+
+#[utoipa::path(get, path = "/teamhistory/{team_num}", responses(
+    (status = OK, body = Vec<TeamSeason>),
+    (status = BAD_REQUEST, description = "`since` is before the oldest season the server hosts or after the newest"),
+), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("since" = Option<u32>, Query, description = "The first year to include, defaults to the oldest season the server hosts")
+)) ]
+async fn get_team_history(
+    State(dm): State<DataManager<CurrentSeason>>,
+    _: Authorized<ReadData>,
+    Path(team_num): Path<u32>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<TeamSeason>>, AppError> {
+    //there is always at least the current season
+    let seasons = dm.get_seasons();
+    let oldest = seasons[0];
+    let until = seasons[seasons.len() - 1];
+    let since = query.since.unwrap_or(oldest);
+    //every year is a statbotics request, so no asking for the whole of history
+    if since < oldest || since > until {
+        return Err(anyhow::anyhow!(StatusCode::BAD_REQUEST)
+            .context(format!("since has to be between {} and {}", oldest, until))
+            .into());
+    }
+    Ok(Json(
+        dm.get_team_history(team_num, (since..=until).collect())
+            .await,
+    ))
 }
 
 //
@@ -244,13 +555,56 @@ struct TeamMatchQuery {
     match_num: u32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScheduleFormat {
+    Json,
+    Csv,
+    Text,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScheduleFormatQuery {
+    format: Option<ScheduleFormat>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyPath {
+    team_num: u32,
+    key_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryQuery {
+    since: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScoutQuery {
+    scout: String,
+}
+
+///Narrows a report lookup down to who recorded it
+#[derive(Debug, Serialize, Deserialize)]
+struct RecorderQuery {
+    recording_team: Option<u32>,
+    scout: Option<String>,
+}
+
+///Scout names are only unique within a team
+fn scout_without_team() -> AppError {
+    AppError(
+        anyhow::anyhow!(StatusCode::BAD_REQUEST).context("scout has to come with a recording_team"),
+    )
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct MatchQuery {
     event: String,
     complevel: Complevel,
     match_num: u32,
 }
-#[utoipa::path(get, path = "/version", responses((status = OK, body = str))) ]
+#[utoipa::path(get, path = "/version", responses((status = OK, body = str)), security(())) ]
 async fn get_server_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
@@ -259,13 +613,18 @@ async fn get_server_version() -> &'static str {
 struct AppError(anyhow::Error);
 
 // Tell axum how to convert `AppError` into a response.
+// Errors that carry a StatusCode (anyhow!(StatusCode::NOT_FOUND), optionally with a context
+// message) get that status, anything else is a 500.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Something went wrong: {}", self.0),
-        )
-            .into_response()
+        match self.0.downcast_ref::<StatusCode>() {
+            Some(status) => (*status, self.0.to_string()).into_response(),
+            None => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Something went wrong: {}", self.0),
+            )
+                .into_response(),
+        }
     }
 }
 
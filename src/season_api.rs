@@ -0,0 +1,530 @@
+// The routes for one season, every season the server hosts gets its own copy of these.
+// This file is not a module, main includes it once per season inside a module that sets `S` to
+// the season (see `season_api!`). The handlers can not just be generic since utoipa can not
+// document a generic handler.
+
+pub fn router() -> OpenApiRouter<DataManager<S>> {
+    OpenApiRouter::new()
+        //not sure I'm happy with how many time i typed route
+        .routes(routes!(get_match_data))
+        .routes(routes!(get_match_check))
+        .routes(routes!(get_scouting_accuracy))
+        .routes(routes!(get_team_data))
+        .routes(routes!(get_team_profile))
+        .routes(routes!(get_team_pit_data, post_team_pit_data))
+        .routes(routes!(get_all_team_pit_data))
+        .routes(routes!(get_avg_team_pit_data))
+        .routes(routes!(get_current_match))
+        .routes(routes!(get_rankings))
+        .routes(routes!(get_team_list))
+        .routes(routes!(get_scouting_assignment))
+        .routes(routes!(get_global_scouting_assignment))
+        .routes(routes!(get_global_match_coverage))
+        .routes(routes!(get_shift_schedule, post_shift_schedule))
+        .routes(routes!(post_shift_check_in))
+        .routes(routes!(post_shift_leave))
+        .routes(routes!(get_team_match_data, post_team_match_data))
+        .routes(routes!(get_all_team_match_data))
+        .routes(routes!(get_all_team_event_match_data))
+        .routes(routes!(get_avg_team_match_data))
+        .routes(routes!(get_avg_team_match_data_for_match))
+        .routes(routes!(get_team_match_consensus))
+        .routes(routes!(get_event_list))
+}
+
+#[utoipa::path(get, path = "/matchdata/{event}/{complevel}/{match_num}", responses((status = 200, body = MatchData<S>)), params(
+        ("event" = String, Path, description = "The event id (blue allience format)"),
+        ("complevel" = Complevel, Path, description = "The level of play"),
+        ("match_num" = u32, Path, description = "the match number")
+    )) ]
+async fn get_match_data(
+    Path(matchd): Path<MatchQuery>,
+    _: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<MatchData<S>>, AppError> {
+    Ok(Json(
+        dm.get_match_data(
+            matchd.event,
+            MatchNumber {
+                number: matchd.match_num,
+                level: matchd.complevel,
+            },
+        )
+        .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/matchcheck/{event}/{complevel}/{match_num}", responses((status = OK, body = Vec<ReportCheck>)), params(
+        ("event" = String, Path, description = "The event id (blue allience format)"),
+        ("complevel" = Complevel, Path, description = "The level of play"),
+        ("match_num" = u32, Path, description = "the match number")
+    )) ]
+async fn get_match_check(
+    Path(matchd): Path<MatchQuery>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<Vec<ReportCheck>>, AppError> {
+    Ok(Json(
+        dm.check_match_reports(
+            auth.viewer(),
+            matchd.event,
+            MatchNumber {
+                number: matchd.match_num,
+                level: matchd.complevel,
+            },
+        )
+        .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/scoutingaccuracy/{event}", responses((status = OK, body = ScoutingAccuracy)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)")
+)) ]
+async fn get_scouting_accuracy(
+    Path(event): Path<String>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<ScoutingAccuracy>, AppError> {
+    Ok(Json(dm.get_scouting_accuracy(auth.viewer(), event).await?))
+}
+
+#[utoipa::path(get, path = "/teamdata/{team_number}/{event}", responses((status = OK, body = data::TeamData)), params(
+    ("team_number" = u32, Path, description = "The team number"),
+    ("event" = String, Path, description = "The event id (blue allience format)")
+)) ]
+async fn get_team_data(
+    Path((team_number, event)): Path<(u32, String)>,
+    _: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<data::TeamData>, AppError> {
+    Ok(Json(dm.get_team_data(team_number, event).await?))
+}
+
+#[utoipa::path(get, path = "/teamprofile/{team_num}/{event}", responses((status = OK, body = TeamProfile<S>)), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("event" = String, Path, description = "The event id (blue allience format)")
+)) ]
+async fn get_team_profile(
+    Path((team_num, event)): Path<(u32, String)>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<TeamProfile<S>>, AppError> {
+    Ok(Json(
+        dm.get_team_profile(auth.viewer(), team_num, event).await?,
+    ))
+}
+
+#[utoipa::path(post, path = "/teammatchdata", responses((status = OK))) ]
+async fn post_team_match_data(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<PostReports>,
+    extract::Json(data): extract::Json<TeamMatchReport<S>>,
+) -> Result<(), AppError> {
+    auth.check_scout(data.recording_team_number, &data.team_member)?;
+    dm.post_team_match_data(data).await?;
+    Ok(())
+}
+
+#[utoipa::path(post, path = "/teampitdata", responses((status = OK))) ]
+async fn post_team_pit_data(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<PostReports>,
+    extract::Json(data): extract::Json<TeamPitReport<S>>,
+) -> Result<(), AppError> {
+    auth.check_scout(data.recording_team, &data.team_member)?;
+    dm.post_team_pit_data(data).await?;
+    Ok(())
+}
+
+#[utoipa::path(get, path = "/teammatchdata/last/{team_num}/{event}/{complevel}/{match_num}", responses((status = OK, body = TeamMatchReport<S>)), params(
+    ("team_num" = u32, Path, description = "the team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("complevel" = Complevel, Path, description = "The level of competition"),
+    ("match_num" = u32, Path, description = "The match number"),
+    ("recording_team" = Option<u32>, Query, description = "Only reports this team recorded"),
+    ("scout" = Option<String>, Query, description = "Only the report this scout on the recording team made"),
+)) ]
+async fn get_team_match_data(
+    Path(matchd): Path<TeamMatchQuery>,
+    Query(recorder): Query<RecorderQuery>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<TeamMatchReport<S>>, AppError> {
+    let match_number = MatchNumber {
+        number: matchd.match_num,
+        level: matchd.complevel,
+    };
+    let viewer = auth.viewer();
+    Ok(Json(match (recorder.recording_team, recorder.scout) {
+        (Some(team), Some(scout)) => {
+            dm.get_team_match_data_by_induvidual(
+                viewer,
+                matchd.team_num,
+                team,
+                scout,
+                match_number,
+                matchd.event,
+            )
+            .await?
+        }
+        (Some(team), None) => {
+            dm.get_last_team_match_data_by_team(
+                viewer,
+                matchd.team_num,
+                team,
+                match_number,
+                matchd.event,
+            )
+            .await?
+        }
+        (None, None) => {
+            dm.get_last_team_match_data(viewer, matchd.team_num, match_number, matchd.event)
+                .await?
+        }
+        (None, Some(_)) => return Err(scout_without_team()),
+    }))
+}
+
+#[utoipa::path(get, path = "/teammatchdata/all/{team_num}/{event}/{complevel}/{match_num}", responses((status = OK, body = Vec<TeamMatchReport<S>>)), params(
+    ("team_num" = u32, Path, description = "the team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("complevel" = Complevel, Path, description = "The level of competition"),
+    ("match_num" = u32, Path, description = "The match number"),
+    ("recording_team" = Option<u32>, Query, description = "Only reports this team recorded"),
+)) ]
+async fn get_all_team_match_data(
+    Path(matchd): Path<TeamMatchQuery>,
+    Query(recorder): Query<RecorderQuery>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<Vec<TeamMatchReport<S>>>, AppError> {
+    let match_number = MatchNumber {
+        number: matchd.match_num,
+        level: matchd.complevel,
+    };
+    let viewer = auth.viewer();
+    Ok(Json(match recorder.recording_team {
+        Some(team) => {
+            dm.get_all_team_match_data_by_team(
+                viewer,
+                matchd.team_num,
+                team,
+                match_number,
+                matchd.event,
+            )
+            .await?
+        }
+        None => {
+            dm.get_all_team_match_data(viewer, matchd.team_num, match_number, matchd.event)
+                .await?
+        }
+    }))
+}
+
+#[utoipa::path(get, path = "/teammatchdata/all/{team_num}/{event}", responses((status = OK, body = Vec<TeamMatchReport<S>>)), params(
+    ("team_num" = u32, Path, description = "the team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+)) ]
+async fn get_all_team_event_match_data(
+    Path((team_num, event)): Path<(u32, String)>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<Vec<TeamMatchReport<S>>>, AppError> {
+    Ok(Json(
+        dm.get_all_team_event_match_data(auth.viewer(), team_num, event)
+            .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/teammatchdata/avg/{team_num}/{event}", responses((status = OK, body = TeamMatchReportAvg<S>)), params(
+    ("team_num" = u32, Path, description = "the team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+)) ]
+async fn get_avg_team_match_data(
+    Path((team_num, event)): Path<(u32, String)>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<TeamMatchReportAvg<S>>, AppError> {
+    Ok(Json(
+        dm.get_avg_team_match_data(auth.viewer(), team_num, None, event)
+            .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/teammatchdata/avg/{team_num}/{event}/{complevel}/{match_num}", responses((status = OK, body = TeamMatchReportAvg<S>)), params(
+    ("team_num" = u32, Path, description = "the team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("complevel" = Complevel, Path, description = "The level of competition"),
+    ("match_num" = u32, Path, description = "The match number"),
+)) ]
+async fn get_avg_team_match_data_for_match(
+    Path(matchd): Path<TeamMatchQuery>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<TeamMatchReportAvg<S>>, AppError> {
+    Ok(Json(
+        dm.get_avg_team_match_data(
+            auth.viewer(),
+            matchd.team_num,
+            Some(MatchNumber {
+                number: matchd.match_num,
+                level: matchd.complevel,
+            }),
+            matchd.event,
+        )
+        .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/teammatchdata/consensus/{team_num}/{event}/{complevel}/{match_num}", responses((status = OK, body = MatchConsensus<S>)), params(
+    ("team_num" = u32, Path, description = "the team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("complevel" = Complevel, Path, description = "The level of competition"),
+    ("match_num" = u32, Path, description = "The match number"),
+)) ]
+async fn get_team_match_consensus(
+    Path(matchd): Path<TeamMatchQuery>,
+    auth: Authorized<ReadData>,
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<MatchConsensus<S>>, AppError> {
+    Ok(Json(
+        dm.get_match_consensus(
+            auth.viewer(),
+            matchd.team_num,
+            MatchNumber {
+                number: matchd.match_num,
+                level: matchd.complevel,
+            },
+            matchd.event,
+        )
+        .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/teampitdata/last/{team_num}/{event}", responses((status = OK, body = TeamPitReport<S>)), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("recording_team" = Option<u32>, Query, description = "Only reports this team recorded"),
+    ("scout" = Option<String>, Query, description = "Only the report this scout on the recording team made"),
+)) ]
+async fn get_team_pit_data(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ReadData>,
+    Path((team_num, event)): Path<(u32, String)>,
+    Query(recorder): Query<RecorderQuery>,
+) -> Result<Json<TeamPitReport<S>>, AppError> {
+    let viewer = auth.viewer();
+    Ok(Json(match (recorder.recording_team, recorder.scout) {
+        (Some(team), Some(scout)) => {
+            dm.get_team_pit_data_by_induvidual(viewer, team_num, team, scout, event)
+                .await?
+        }
+        (Some(team), None) => {
+            dm.get_last_team_pit_data_by_team(viewer, team_num, team, event)
+                .await?
+        }
+        (None, None) => dm.get_last_team_pit_data(viewer, team_num, event).await?,
+        (None, Some(_)) => return Err(scout_without_team()),
+    }))
+}
+
+#[utoipa::path(get, path = "/teampitdata/all/{team_num}/{event}", responses((status = OK, body = Vec<TeamPitReport<S>>)), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("recording_team" = Option<u32>, Query, description = "Only reports this team recorded"),
+)) ]
+async fn get_all_team_pit_data(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ReadData>,
+    Path((team_num, event)): Path<(u32, String)>,
+    Query(recorder): Query<RecorderQuery>,
+) -> Result<Json<Vec<TeamPitReport<S>>>, AppError> {
+    let viewer = auth.viewer();
+    Ok(Json(match recorder.recording_team {
+        Some(team) => {
+            dm.get_all_team_pit_data_by_team(viewer, team_num, team, event)
+                .await?
+        }
+        None => dm.get_all_team_pit_data(viewer, team_num, event).await?,
+    }))
+}
+
+#[utoipa::path(get, path = "/teampitdata/avg/{team_num}/{event}", responses((status = OK, body = PitConsensus<S>)), params(
+    ("team_num" = u32, Path, description = "The team number"),
+    ("event" = String, Path, description = "The event id (blue alliance format)")
+)) ]
+async fn get_avg_team_pit_data(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ReadData>,
+    Path((team_num, event)): Path<(u32, String)>,
+) -> Result<Json<PitConsensus<S>>, AppError> {
+    Ok(Json(
+        dm.get_avg_team_pit_data(auth.viewer(), team_num, event)
+            .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/scoutingassignment/{event}/{match_num}", responses((status = OK, body = ScoutingAssignment)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("match_num" = u32, Path, description = "The qualification match number"),
+    ("scout" = String, Query, description = "Who is asking, the same scout always gets the same robot in a match")
+)) ]
+async fn get_scouting_assignment(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ScoutMatches>,
+    Path((event, match_num)): Path<(String, u32)>,
+    Query(query): Query<ScoutQuery>,
+) -> Result<Json<ScoutingAssignment>, AppError> {
+    let team = auth.team()?;
+    auth.check_scout(team, &query.scout)?;
+    Ok(Json(
+        dm.get_team_scouting_assignment(event, team, match_num, query.scout)
+            .await?,
+    ))
+}
+
+#[axum::debug_handler]
+#[utoipa::path(get, path = "/eventlist", responses((status = OK, body = Vec<Eventdata>)), security(())) ]
+async fn get_event_list(
+    State(dm): State<DataManager<S>>,
+) -> Result<Json<Vec<Eventdata>>, AppError> {
+    Ok(Json(dm.get_event_data().await?))
+}
+
+#[utoipa::path(get, path = "/scoutingassignment/global/{event}/{match_num}", responses((status = OK, body = ScoutingAssignment)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("match_num" = u32, Path, description = "The qualification match number"),
+    ("scout" = String, Query, description = "Who is asking, the same scout always gets the same robot in a match")
+)) ]
+async fn get_global_scouting_assignment(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ScoutMatches>,
+    Path((event, match_num)): Path<(String, u32)>,
+    Query(query): Query<ScoutQuery>,
+) -> Result<Json<ScoutingAssignment>, AppError> {
+    let team = auth.team()?;
+    auth.check_scout(team, &query.scout)?;
+    Ok(Json(
+        dm.get_global_scouting_assignment(event, team, match_num, query.scout)
+            .await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/scoutingassignment/global/{event}/{match_num}/coverage", responses((status = OK, body = MatchCoverage)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("match_num" = u32, Path, description = "The qualification match number")
+)) ]
+async fn get_global_match_coverage(
+    State(dm): State<DataManager<S>>,
+    _: Authorized<ReadData>,
+    Path((event, match_num)): Path<(String, u32)>,
+) -> Result<Json<MatchCoverage>, AppError> {
+    Ok(Json(dm.get_global_match_coverage(event, match_num)?))
+}
+
+#[utoipa::path(get, path = "/rankings/{event}", responses((status = OK, body = Vec<Ranking>)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)")
+)) ]
+async fn get_rankings(
+    State(dm): State<DataManager<S>>,
+    _: Authorized<ReadData>,
+    Path(event): Path<String>,
+) -> Result<Json<Vec<Ranking>>, AppError> {
+    Ok(Json(dm.get_rankings(event).await?))
+}
+
+#[utoipa::path(get, path = "/teamlist/{event}", responses((status = OK, body = Vec<u32>)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)")
+)) ]
+async fn get_team_list(
+    State(dm): State<DataManager<S>>,
+    _: Authorized<ReadData>,
+    Path(event): Path<String>,
+) -> Result<Json<Vec<u32>>, AppError> {
+    Ok(Json(dm.get_team_list(event).await?))
+}
+
+#[utoipa::path(get, path = "/currentmatch/{event}", responses((status = OK, body = CurrentMatch)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)")
+)) ]
+async fn get_current_match(
+    State(dm): State<DataManager<S>>,
+    _: Authorized<ScoutMatches>,
+    Path(event): Path<String>,
+) -> Result<Json<CurrentMatch>, AppError> {
+    Ok(Json(dm.get_current_match(event).await?))
+}
+
+#[utoipa::path(post, path = "/shiftschedule/{event}", request_body = ShiftRequest, responses((status = OK, body = ShiftSchedule)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)")
+)) ]
+async fn post_shift_schedule(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ManageScouts>,
+    Path(event): Path<String>,
+    Json(request): Json<ShiftRequest>,
+) -> Result<Json<ShiftSchedule>, AppError> {
+    Ok(Json(
+        dm.set_shift_schedule(event, auth.team()?, request).await?,
+    ))
+}
+
+#[utoipa::path(get, path = "/shiftschedule/{event}", responses(
+    (status = OK, body = ShiftSchedule, content_type = "application/json"),
+    (status = OK, body = String, content_type = "text/csv"),
+    (status = OK, body = String, content_type = "text/plain")
+), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("format" = Option<String>, Query, description = "json (the default), csv or text for a printable version")
+)) ]
+async fn get_shift_schedule(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ScoutMatches>,
+    Path(event): Path<String>,
+    Query(query): Query<ScheduleFormatQuery>,
+) -> Result<Response, AppError> {
+    let schedule = dm.get_shift_schedule(event, auth.team()?)?;
+
+    Ok(match query.format.unwrap_or(ScheduleFormat::Json) {
+        ScheduleFormat::Json => Json(schedule).into_response(),
+        ScheduleFormat::Csv => ([(CONTENT_TYPE, "text/csv")], schedule.to_csv()).into_response(),
+        ScheduleFormat::Text => schedule.to_text().into_response(),
+    })
+}
+
+#[utoipa::path(post, path = "/shiftschedule/{event}/checkin/{match_num}", responses((status = OK, body = ShiftSchedule)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("match_num" = u32, Path, description = "The first qualifier the scout is around for"),
+    ("scout" = String, Query, description = "The scout that showed up")
+)) ]
+async fn post_shift_check_in(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ScoutMatches>,
+    Path((event, match_num)): Path<(String, u32)>,
+    Query(query): Query<ScoutQuery>,
+) -> Result<Json<ShiftSchedule>, AppError> {
+    let team = auth.team()?;
+    auth.check_scout(team, &query.scout)?;
+    Ok(Json(
+        dm.shift_check_in(event, team, query.scout, match_num)
+            .await?,
+    ))
+}
+
+#[utoipa::path(post, path = "/shiftschedule/{event}/leave/{match_num}", responses((status = OK, body = ShiftSchedule)), params(
+    ("event" = String, Path, description = "The event id (blue alliance format)"),
+    ("match_num" = u32, Path, description = "The first qualifier the scout is gone for"),
+    ("scout" = String, Query, description = "The scout that left")
+)) ]
+async fn post_shift_leave(
+    State(dm): State<DataManager<S>>,
+    auth: Authorized<ScoutMatches>,
+    Path((event, match_num)): Path<(String, u32)>,
+    Query(query): Query<ScoutQuery>,
+) -> Result<Json<ShiftSchedule>, AppError> {
+    let team = auth.team()?;
+    auth.check_scout(team, &query.scout)?;
+    Ok(Json(
+        dm.shift_leave(event, team, query.scout, match_num).await?,
+    ))
+}